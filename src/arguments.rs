@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::profile::VersionProfile;
+use crate::rules::RuleContext;
+use crate::settings::GameOptions;
+use crate::setup;
+
+// Builds jvm and game argument lists from version json files.
+//
+// Newer versions (1.13+) describe their arguments in
+// `arguments.game` / `arguments.jvm`, where every entry is either
+// a plain string or an object with rules and one or more values.
+// Older versions only have a single `minecraftArguments` string
+// and rely on the launcher for the jvm arguments.
+//
+// Arguments are kept as templates, placeholders like
+// `${auth_access_token}` are replaced with `substitute`.
+//
+// Game arguments that depend on launch options (window size, quick
+// play) are kept as they are in the json and only evaluated at
+// launch, see `game_option_arguments`.
+
+#[derive(Clone, Debug, Default)]
+pub struct Arguments {
+    pub jvm: Vec<String>,
+    pub game: Vec<String>,
+    pub conditional_game: Vec<Value>,
+}
+
+impl Arguments {
+    // collect arguments of a single version json
+    pub fn from_version(json: &Value, rules: &RuleContext) -> Arguments {
+        let mut jvm = Vec::new();
+        let mut game = Vec::new();
+        let mut conditional_game = Vec::new();
+
+        if json["arguments"].is_object() {
+            jvm = collect(&json["arguments"]["jvm"], rules);
+
+            let (conditional, plain): (Vec<Value>, Vec<Value>) = json["arguments"]["game"]
+                .as_array()
+                .cloned()
+                .unwrap_or_default()
+                .into_iter()
+                .partition(uses_features);
+
+            game = collect(&Value::Array(plain), rules);
+            conditional_game = conditional;
+        } else if let Some(args) = json["minecraftArguments"].as_str() {
+            jvm = legacy_jvm_arguments(rules);
+            game = args.split_whitespace().map(|a| a.to_string()).collect();
+        }
+
+        Arguments { jvm, game, conditional_game }
+    }
+
+    // arguments of a profile installed in `instance_path` with all
+    // setup time placeholders filled in
+    pub fn from_profile(profile: &VersionProfile, rules: &RuleContext, instance_path: &Path) -> Arguments {
+        let game_assets = setup::game_assets(instance_path, profile);
+
+        let mut arguments = Arguments::from_version(profile.json(), rules);
+        arguments.jvm.extend(setup::logging_arguments(instance_path, profile));
+        arguments.substitute(&setup_vars(profile.json(), profile.id.as_str(), game_assets.as_str()));
+        arguments
+    }
+
+    pub fn substitute(&mut self, vars: &HashMap<String, String>) {
+        self.jvm = self.jvm.iter().map(|a| substitute(a, vars)).collect();
+        self.game = self.game.iter().map(|a| substitute(a, vars)).collect();
+    }
+}
+
+// evaluate an `arguments.game` or `arguments.jvm` array
+pub fn collect(args: &Value, rules: &RuleContext) -> Vec<String> {
+    let mut retvec = Vec::new();
+
+    let args = match args.as_array() {
+        Some(val) => val,
+        None => return retvec,
+    };
+
+    for arg in args {
+        match arg {
+            Value::String(s) => retvec.push(s.clone()),
+            Value::Object(_) => {
+                if !rules.allows(&arg["rules"]) {
+                    continue;
+                }
+
+                match &arg["value"] {
+                    Value::String(s) => retvec.push(s.clone()),
+                    Value::Array(values) => {
+                        for value in values {
+                            if let Some(s) = value.as_str() {
+                                retvec.push(s.to_string());
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            _ => (),
+        }
+    }
+
+    retvec
+}
+
+fn uses_features(arg: &Value) -> bool {
+    match arg["rules"].as_array() {
+        Some(rules) => rules.iter().any(|rule| rule["features"].is_object()),
+        None => false,
+    }
+}
+
+// whether a version has arguments for a launcher feature
+pub fn declares_feature(conditional: &[Value], feature: &str) -> bool {
+    conditional.iter().any(|arg| {
+        arg["rules"]
+            .as_array()
+            .is_some_and(|rules| rules.iter().any(|rule| !rule["features"][feature].is_null()))
+    })
+}
+
+// Game arguments for the window and quick play options. Versions
+// that describe them (window size since 1.13, quick play since
+// 1.20) get their own arguments, older ones get the arguments
+// launchers have always passed.
+pub fn game_option_arguments(conditional: &[Value], options: &GameOptions) -> Result<Vec<String>, String> {
+    options.validate()?;
+
+    let mut vars = HashMap::new();
+    let mut rules = RuleContext::current();
+    let mut fallback: Vec<String> = Vec::new();
+
+    let resolution = options.width.is_some() || options.height.is_some();
+    if resolution {
+        // the game's default window size
+        let width = options.width.unwrap_or(854).to_string();
+        let height = options.height.unwrap_or(480).to_string();
+
+        if declares_feature(conditional, "has_custom_resolution") {
+            rules = rules.with_feature("has_custom_resolution", true);
+            vars.insert("resolution_width".to_string(), width);
+            vars.insert("resolution_height".to_string(), height);
+        } else {
+            fallback.extend(vec!["--width".to_string(), width, "--height".to_string(), height]);
+        }
+    }
+
+    if let Some(server) = &options.server {
+        if declares_feature(conditional, "is_quick_play_multiplayer") {
+            let address = match options.port {
+                Some(port) => format!("{}:{}", server, port),
+                None => server.clone(),
+            };
+            rules = rules.with_feature("is_quick_play_multiplayer", true);
+            vars.insert("quickPlayMultiplayer".to_string(), address);
+        } else {
+            let port = options.port.unwrap_or(25565).to_string();
+            fallback.extend(vec!["--server".to_string(), server.clone(), "--port".to_string(), port]);
+        }
+    }
+
+    for (target, feature, var) in [
+        (&options.world, "is_quick_play_singleplayer", "quickPlaySingleplayer"),
+        (&options.realm, "is_quick_play_realms", "quickPlayRealms"),
+    ] {
+        if let Some(target) = target {
+            if !declares_feature(conditional, feature) {
+                return Err("This version can't join a world or realm directly, that needs minecraft 1.20 or newer".to_string());
+            }
+            rules = rules.with_feature(feature, true);
+            vars.insert(var.to_string(), target.clone());
+        }
+    }
+
+    if options.fullscreen == Some(true) {
+        fallback.push("--fullscreen".to_string());
+    }
+
+    let mut retvec: Vec<String> = collect(&Value::Array(conditional.to_vec()), &rules)
+        .iter()
+        .map(|arg| substitute(arg, &vars))
+        .collect();
+    retvec.extend(fallback);
+
+    Ok(retvec)
+}
+
+// the jvm arguments launchers used before `arguments.jvm` existed
+pub fn legacy_jvm_arguments(rules: &RuleContext) -> Vec<String> {
+    let mut args = Vec::new();
+
+    if rules.os_name == "osx" {
+        args.push("-XstartOnFirstThread".to_string());
+    }
+
+    args.push("-Djava.library.path=${natives_directory}".to_string());
+    args.push("-cp".to_string());
+    args.push("${classpath}".to_string());
+
+    args
+}
+
+// Replaces every `${name}` with its value, unknown
+// placeholders are left as they are so they can be
+// filled in later
+pub fn substitute(arg: &str, vars: &HashMap<String, String>) -> String {
+    let mut retstr = String::new();
+    let mut rest = arg;
+
+    while let Some(start) = rest.find("${") {
+        retstr.push_str(&rest[..start]);
+
+        match rest[start..].find('}') {
+            Some(len) => {
+                let name = &rest[start + 2..start + len];
+                match vars.get(name) {
+                    Some(value) => retstr.push_str(value),
+                    None => retstr.push_str(&rest[start..start + len + 1]),
+                }
+                rest = &rest[start + len + 1..];
+            }
+            None => {
+                retstr.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+
+    retstr.push_str(rest);
+    retstr
+}
+
+// Placeholders that are known once the version is installed.
+// Everything that can change later on (user info, classpath)
+// is filled in by the invoker at launch.
+pub fn setup_vars(json: &Value, version_name: &str, game_assets: &str) -> HashMap<String, String> {
+    let mut vars = HashMap::new();
+
+    let asset_index = json["assetIndex"]["id"]
+        .as_str()
+        .or_else(|| json["assets"].as_str())
+        .unwrap_or("legacy");
+
+    let version_type = json["type"].as_str().unwrap_or("release");
+
+    vars.insert("version_name".to_string(), version_name.to_string());
+    vars.insert("version_type".to_string(), version_type.to_string());
+    vars.insert("game_directory".to_string(), ".".to_string());
+    vars.insert("assets_root".to_string(), "./assets".to_string());
+    vars.insert("game_assets".to_string(), game_assets.to_string());
+    vars.insert("assets_index_name".to_string(), asset_index.to_string());
+    vars.insert("library_directory".to_string(), "./libraries".to_string());
+    vars.insert("classpath_separator".to_string(), classpath_separator().to_string());
+    vars.insert("launcher_name".to_string(), "sml".to_string());
+    vars.insert("launcher_version".to_string(), env!("CARGO_PKG_VERSION").to_string());
+
+    vars
+}
+
+pub fn classpath_separator() -> &'static str {
+    if cfg!(windows) {
+        ";"
+    } else {
+        ":"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn conditional(id: &str) -> Vec<Value> {
+        let versions = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/versions");
+        let profile = VersionProfile::load(&versions, id).unwrap();
+        Arguments::from_version(profile.json(), &RuleContext::new("linux", "x86_64", "6.1.0")).conditional_game
+    }
+
+    fn options(key: &str, value: &str) -> GameOptions {
+        let mut options = GameOptions::default();
+        options.set(key, value).unwrap();
+        options
+    }
+
+    #[test]
+    fn game_options_modern() {
+        let args = conditional("1.20.1");
+        assert!(declares_feature(&args, "is_quick_play_realms"));
+
+        assert!(game_option_arguments(&args, &GameOptions::default()).unwrap().is_empty());
+
+        let mut window = options("width", "1280");
+        window.fullscreen = Some(true);
+        assert_eq!(
+            game_option_arguments(&args, &window).unwrap(),
+            vec!["--width", "1280", "--height", "480", "--fullscreen"]
+        );
+
+        let mut server = options("server", "mc.example.com");
+        server.set("port", "25566").unwrap();
+        assert_eq!(
+            game_option_arguments(&args, &server).unwrap(),
+            vec!["--quickPlayMultiplayer", "mc.example.com:25566"]
+        );
+
+        assert_eq!(
+            game_option_arguments(&args, &options("world", "New World")).unwrap(),
+            vec!["--quickPlaySingleplayer", "New World"]
+        );
+    }
+
+    #[test]
+    fn game_options_legacy() {
+        let args = conditional("1.12.2");
+        assert!(args.is_empty());
+
+        assert_eq!(
+            game_option_arguments(&args, &options("server", "mc.example.com")).unwrap(),
+            vec!["--server", "mc.example.com", "--port", "25565"]
+        );
+        assert_eq!(
+            game_option_arguments(&args, &options("height", "720")).unwrap(),
+            vec!["--width", "854", "--height", "720"]
+        );
+        assert!(game_option_arguments(&args, &options("realm", "12345")).is_err());
+
+        // 1.16.5 knows about window sizes but not quick play
+        let args = conditional("1.16.5");
+        assert!(declares_feature(&args, "has_custom_resolution"));
+        assert_eq!(
+            game_option_arguments(&args, &options("server", "mc.example.com")).unwrap(),
+            vec!["--server", "mc.example.com", "--port", "25565"]
+        );
+    }
+}
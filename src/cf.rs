@@ -100,10 +100,7 @@ impl CFProject {
 
         println!("Choose version (Enter a number): ");
 
-        let choice: u64 = match util::get_u64() {
-            Some(n) => n,
-            None => 0,
-        };
+        let choice: u64 = util::get_u64().unwrap_or_default();
 
         Ok(choice as usize)
     }
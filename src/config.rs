@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use log::warn;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::util;
+
+// Launcher wide settings, stored in config.json in the sml
+// directory. Missing keys (or a missing file) fall back to the
+// defaults below.
+
+pub const DEFAULT_RUNTIME_MANIFEST: &str =
+    "https://launchermeta.mojang.com/v1/products/java-runtime/2ec0cc96c44e5a76b9c8b7c39df7210883d12871/all.json";
+
+// Mod loader to install instead of the one a modpack asks for.
+// `from` matches a loader id exactly, `pattern` is a regex
+// matched against it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoaderOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    pub to: String,
+}
+
+impl LoaderOverride {
+    pub fn matches(&self, loader_id: &str) -> bool {
+        if self.from.as_deref() == Some(loader_id) {
+            return true;
+        }
+
+        match &self.pattern {
+            Some(pattern) => match Regex::new(pattern) {
+                Ok(re) => re.is_match(loader_id),
+                Err(e) => {
+                    warn!("Invalid loader override pattern {}: {}", pattern, e);
+                    false
+                }
+            },
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // index of the java runtimes mojang provides
+    pub runtime_manifest_url: String,
+    pub loader_overrides: Vec<LoaderOverride>,
+    // wrapper command and environment for every instance, the
+    // instance's own go inside / on top of these
+    pub wrapper: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            runtime_manifest_url: DEFAULT_RUNTIME_MANIFEST.to_string(),
+            loader_overrides: default_loader_overrides(),
+            wrapper: Vec::new(),
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+// the 1.12.2 forge builds sml always swapped for 14.23.5.2855
+fn default_loader_overrides() -> Vec<LoaderOverride> {
+    ["forge-14.23.5.2838", "forge-1.12.2-14.23.5.2847"]
+        .iter()
+        .map(|id| LoaderOverride {
+            from: Some(id.to_string()),
+            pattern: None,
+            to: "forge-14.23.5.2855".to_string(),
+        })
+        .collect()
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        util::get_sml_path().map(|mut pb| {
+            pb.push("config.json");
+            pb
+        })
+    }
+
+    pub fn load() -> Config {
+        let path = match Config::path() {
+            Some(path) => path,
+            None => return Config::default(),
+        };
+
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(_) => return Config::default(),
+        };
+
+        match serde_json::from_str(data.as_str()) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Invalid config at {}, using defaults: {}", path.display(), e);
+                Config::default()
+            }
+        }
+    }
+
+    // first override that applies to a loader id
+    pub fn loader_override(&self, loader_id: &str) -> Option<&LoaderOverride> {
+        self.loader_overrides.iter().find(|o| o.matches(loader_id))
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Config::path().ok_or("Couldn't find the sml directory")?;
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,448 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ansi_term::Colour::{Red, Yellow};
+use regex::Regex;
+
+use crate::invoker;
+
+// Reading crash reports.
+//
+// The game writes a report to `crash-reports/` when it crashes in
+// a way it can handle. When it dies before that (e.g. while mods
+// load) the only trace is its output, which attached launches keep
+// in `logs/sml-launch-*.log`.
+
+const REPORT_HEADER: &str = "---- Minecraft Crash Report ----";
+
+#[derive(Clone, Debug, Default)]
+pub struct ModEntry {
+    pub id: String,
+    pub version: String,
+    pub file: String,
+    pub errored: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CrashSummary {
+    // crash report or launch log it was read from
+    pub source: PathBuf,
+    pub description: Option<String>,
+    pub exception: Option<String>,
+    // last "Caused by" of the exception
+    pub cause: Option<String>,
+    pub suspected_mods: Vec<String>,
+    pub mods: Vec<ModEntry>,
+    pub hints: Vec<String>,
+}
+
+impl CrashSummary {
+    pub fn from_report(path: &Path) -> Option<CrashSummary> {
+        let text = fs::read_to_string(path).ok()?;
+        let mut summary = parse_report(text.as_str());
+        summary.source = path.to_path_buf();
+        Some(summary)
+    }
+
+    // None if the log doesn't show an exception
+    pub fn from_log(path: &Path) -> Option<CrashSummary> {
+        let text = fs::read_to_string(path).ok()?;
+        let mut summary = parse_log(text.as_str())?;
+        summary.source = path.to_path_buf();
+        Some(summary)
+    }
+
+    pub fn display(&self) {
+        println!("{} {}", Red.paint("Crash:"), self.source.display());
+
+        if let Some(description) = &self.description {
+            println!("  Description: {}", description);
+        }
+        if let Some(exception) = &self.exception {
+            println!("  Exception: {}", exception);
+        }
+        if let Some(cause) = &self.cause {
+            println!("  Caused by: {}", cause);
+        }
+        if !self.suspected_mods.is_empty() {
+            println!("  Suspected mods: {}", self.suspected_mods.join(", "));
+        }
+
+        if !self.mods.is_empty() {
+            let errored: Vec<String> = self.mods
+                .iter()
+                .filter(|m| m.errored)
+                .map(|m| format!("{} {}", m.id, m.version))
+                .collect();
+
+            if errored.is_empty() {
+                println!("  Mods: {}", self.mods.len());
+            } else {
+                println!("  Mods: {}, errored: {}", self.mods.len(), errored.join(", "));
+            }
+        }
+
+        for hint in &self.hints {
+            println!("  {} {}", Yellow.paint("Hint:"), hint);
+        }
+    }
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+pub fn parse_report(text: &str) -> CrashSummary {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut summary = CrashSummary::default();
+
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+
+        if summary.description.is_none() {
+            if let Some(description) = trimmed.strip_prefix("Description:") {
+                summary.description = Some(description.trim().to_string());
+
+                // the exception follows the description, its stack
+                // trace ends at the first empty line
+                let mut stack = lines[i + 1..].iter().skip_while(|l| l.trim().is_empty());
+                summary.exception = stack.next().map(|l| l.trim().to_string());
+                summary.cause = stack
+                    .take_while(|l| !l.trim().is_empty())
+                    .filter_map(|l| l.trim().strip_prefix("Caused by:"))
+                    .last()
+                    .map(|c| c.trim().to_string());
+                continue;
+            }
+        }
+
+        if trimmed.starts_with("Suspected Mod") {
+            summary.suspected_mods.extend(suspected_mods(&lines[i..]));
+        } else if trimmed.starts_with("Mod List:") {
+            summary.mods.extend(mod_list(&lines[i + 1..]));
+        } else if trimmed.starts_with("| State") && trimmed.contains("| ID") {
+            summary.mods.extend(mod_states(&lines[i + 1..]));
+        }
+    }
+
+    summary.hints = hints(text);
+    summary
+}
+
+// "Suspected Mods: NONE", or one mod per indented line below:
+//  Suspected Mod:
+//      Example Mod (examplemod), Version: 1.0
+//          Issue tracker URL: ...
+fn suspected_mods(lines: &[&str]) -> Vec<String> {
+    let mut retvec = Vec::new();
+
+    let header = lines[0];
+    let inline = header.split_once(':').map(|(_, v)| v.trim()).unwrap_or("");
+    if !inline.is_empty() && inline != "NONE" {
+        retvec.push(inline.to_string());
+    }
+
+    let mod_indent = match lines.get(1) {
+        Some(line) if indentation(line) > indentation(header) => indentation(line),
+        _ => return retvec,
+    };
+
+    for line in &lines[1..] {
+        let indent = indentation(line);
+        if line.trim().is_empty() || indent < mod_indent {
+            break;
+        }
+        if indent == mod_indent {
+            retvec.push(line.trim().to_string());
+        }
+    }
+
+    retvec
+}
+
+// 1.16+: file |name |id |version |state |manifest
+fn mod_list(lines: &[&str]) -> Vec<ModEntry> {
+    lines
+        .iter()
+        .take_while(|l| l.contains('|'))
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split('|').map(|c| c.trim()).collect();
+            if columns.len() < 5 {
+                return None;
+            }
+            Some(ModEntry {
+                id: columns[2].to_string(),
+                version: columns[3].to_string(),
+                file: columns[0].to_string(),
+                errored: columns[4] == "ERROR",
+            })
+        })
+        .collect()
+}
+
+// 1.12: | State | ID | Version | Source | Signature |
+// every letter of the state is a loading stage, E means errored
+fn mod_states(lines: &[&str]) -> Vec<ModEntry> {
+    lines
+        .iter()
+        .map(|l| l.trim())
+        .take_while(|l| l.starts_with('|'))
+        .filter(|l| !l.starts_with("|:"))
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.trim_matches('|').split('|').map(|c| c.trim()).collect();
+            if columns.len() < 4 {
+                return None;
+            }
+            Some(ModEntry {
+                id: columns[1].to_string(),
+                version: columns[2].to_string(),
+                file: columns[3].to_string(),
+                errored: columns[0].contains('E'),
+            })
+        })
+        .collect()
+}
+
+// Game output of a launch. Forge copies crash reports it writes to
+// the output, otherwise the first exception is all there is.
+pub fn parse_log(text: &str) -> Option<CrashSummary> {
+    if let Some(start) = text.find(REPORT_HEADER) {
+        return Some(parse_report(&text[start..]));
+    }
+
+    let exception = Regex::new(r#"(?m)^(?:Exception in thread "[^"]*" )?((?:[A-Za-z_$][\w$]*\.)+[\w$]*(?:Exception|Error)\b.*)$"#).unwrap();
+    let captures = exception.captures(text)?;
+
+    Some(CrashSummary {
+        exception: Some(captures[1].trim().to_string()),
+        hints: hints(text),
+        ..CrashSummary::default()
+    })
+}
+
+// Known causes of crashes, worded as what to check
+pub fn hints(text: &str) -> Vec<String> {
+    let mut retvec: Vec<String> = Vec::new();
+
+    let class_version = Regex::new(r"class file versions? (?:up to )?(\d+)").unwrap();
+    if text.contains("UnsupportedClassVersionError") {
+        match class_version.captures(text).and_then(|c| c[1].parse::<u32>().ok()) {
+            // class file version 52 is java 8, 61 is java 17, ...
+            Some(version) if version > 44 => retvec.push(format!(
+                "Something was built for java {}, check which java the instance uses",
+                version - 44
+            )),
+            _ => retvec.push("Something was built for a newer java, check which java the instance uses".to_string()),
+        }
+    }
+
+    if text.contains("Unsupported class file major version")
+        || text.contains("cannot be cast to class java.net.URLClassLoader")
+    {
+        retvec.push("This version needs java 8, it was started with a newer java".to_string());
+    }
+
+    let dependency = Regex::new(r"Mod ID: '([^']+)', Requested by: '([^']+)'").unwrap();
+    for captures in dependency.captures_iter(text) {
+        retvec.push(format!("{} needs {}, which is missing or the wrong version", &captures[2], &captures[1]));
+    }
+    if text.contains("MissingModsException") || text.contains("Missing or unsupported mandatory dependencies") {
+        retvec.push("A mod is missing one of its dependencies".to_string());
+    }
+
+    if text.contains("NoClassDefFoundError") || text.contains("ClassNotFoundException") {
+        retvec.push("A class couldn't be found, usually a missing dependency or a mod for another minecraft version".to_string());
+    }
+
+    if text.contains("DuplicateModsFoundException") || text.contains("Found duplicate mods") {
+        retvec.push("Some mods are installed twice, check the mods folder".to_string());
+    }
+
+    if text.contains("OutOfMemoryError") {
+        retvec.push("The game ran out of memory, raise the instance's maximum heap".to_string());
+    }
+
+    if text.contains("MixinApplyError") || text.contains("InvalidMixinException") || text.contains("Mixin apply failed") {
+        retvec.push("A mod's mixin failed to apply, usually incompatible mods or mod versions".to_string());
+    }
+
+    if text.contains("Pixel format not accelerated") || text.contains("GLFW error 65542") || text.contains("does not appear to support OpenGL") {
+        retvec.push("OpenGL isn't available, check the graphics drivers".to_string());
+    }
+
+    retvec.dedup();
+    retvec
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn newest(paths: Vec<PathBuf>) -> Option<PathBuf> {
+    paths.into_iter().max_by_key(|p| modified(p))
+}
+
+fn crash_reports(instance_path: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(instance_path.join("crash-reports")) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn launch_logs(instance_path: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(instance_path.join("logs")) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| invoker::is_launch_log(p))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// newest crash report written since `started`
+pub fn report_since(instance_path: &Path, started: SystemTime) -> Option<CrashSummary> {
+    newest(crash_reports(instance_path))
+        .filter(|report| modified(report).is_some_and(|time| time >= started))
+        .and_then(|report| CrashSummary::from_report(&report))
+}
+
+// Crash of a launch that started at `started`: a crash report
+// written since then, or the launch log if the game failed
+pub fn detect(instance_path: &Path, started: SystemTime, code: Option<i32>, log_path: &Path) -> Option<CrashSummary> {
+    if let Some(summary) = report_since(instance_path, started) {
+        return Some(summary);
+    }
+
+    if code == Some(0) {
+        return None;
+    }
+
+    CrashSummary::from_log(log_path).or_else(|| {
+        Some(CrashSummary {
+            source: log_path.to_path_buf(),
+            ..CrashSummary::default()
+        })
+    })
+}
+
+// The last crash of an instance, from its newest crash report or
+// launch log, whichever is more recent
+pub fn last_crash(instance_path: &Path) -> Option<CrashSummary> {
+    let report = newest(crash_reports(instance_path));
+    let log = newest(launch_logs(instance_path));
+
+    match (report, log) {
+        (Some(report), Some(log)) if modified(&log) > modified(&report) => {
+            CrashSummary::from_log(&log).or_else(|| CrashSummary::from_report(&report))
+        }
+        (Some(report), _) => CrashSummary::from_report(&report),
+        (None, Some(log)) => CrashSummary::from_log(&log),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODERN_REPORT: &str = "---- Minecraft Crash Report ----
+// Shall we play a game?
+
+Time: 2023-06-12 18:03:11
+Description: Mod loading error has occurred
+
+java.lang.Exception: Mod Loading has failed
+\tat net.minecraftforge.logging.CrashReportExtender.dumpModLoadingCrashReport(CrashReportExtender.java:60) ~[forge-1.20.1-47.0.19-universal.jar%23191!/:?] {re:classloading}
+\tat net.minecraftforge.client.loading.ClientModLoader.completeModLoading(ClientModLoader.java:172) ~[forge-1.20.1-47.0.19-universal.jar%23191!/:?] {re:classloading}
+Caused by: java.lang.NoClassDefFoundError: dev/architectury/event/Event
+\tat example.Mod.<init>(Mod.java:10) ~[?:?]
+
+A detailed walkthrough of the error, its code path and all known details is as follows:
+---------------------------------------------------------------------------------------
+
+-- MOD rei --
+Details:
+\tMod File: /instances/x/mods/rei.jar
+\tFailure message: Roughly Enough Items (rei) has failed to load correctly
+\t\tjava.lang.NoClassDefFoundError: dev/architectury/event/Event
+\tSuspected Mods:
+\t\tRoughly Enough Items (rei), Version: 12.0.625
+\t\t\tIssue tracker URL: https://github.com/shedaniel/RoughlyEnoughItems/issues
+\t\t\tat TRANSFORMER/rei@12.0.625/me.shedaniel.rei.RoughlyEnoughItemsCore.<init>(RoughlyEnoughItemsCore.java:60)
+
+-- System Details --
+Details:
+\tMinecraft Version: 1.20.1
+\tMod List:
+\t\tforge-1.20.1-47.0.19-universal.jar               |Forge                         |forge                         |47.0.19             |DONE      |Manifest: NOSIGNATURE
+\t\tRoughlyEnoughItems-12.0.625.jar                   |Roughly Enough Items (REI)    |rei                           |12.0.625            |ERROR     |Manifest: NOSIGNATURE
+\t\tclient-1.20.1-20230612.114412-srg.jar             |Minecraft                     |minecraft                     |1.20.1              |DONE      |Manifest: a1:d4:5e
+\tCrash Report UUID: 0f0f0f0f-0000-0000-0000-000000000000
+";
+
+    const LEGACY_REPORT: &str = "---- Minecraft Crash Report ----
+Description: There was a severe problem during mod loading that has caused the game to fail
+
+net.minecraftforge.fml.common.MissingModsException: Mod jei (Just Enough Items) requires [forge@[14.23.5.2859,)]
+\tat net.minecraftforge.fml.common.Loader.sortModList(Loader.java:266)
+
+-- System Details --
+\tStates: 'U' = Unloaded 'L' = Loaded 'C' = Constructed 'H' = Pre-initialized 'I' = Initialized 'J' = Post-initialized 'A' = Available 'D' = Disabled 'E' = Errored
+
+\t| State | ID        | Version      | Source                        | Signature                                |
+\t|:----- |:--------- |:------------ |:----------------------------- |:---------------------------------------- |
+\t| L     | minecraft | 1.12.2       | minecraft.jar                 | None                                     |
+\t| LE    | jei       | 4.16.1.301   | jei_1.12.2-4.16.1.301.jar     | None                                     |
+
+\tLoaded coremods (and transformers):
+";
+
+    #[test]
+    fn modern_report() {
+        let summary = parse_report(MODERN_REPORT);
+
+        assert_eq!(summary.description.as_deref(), Some("Mod loading error has occurred"));
+        assert_eq!(summary.exception.as_deref(), Some("java.lang.Exception: Mod Loading has failed"));
+        assert_eq!(summary.cause.as_deref(), Some("java.lang.NoClassDefFoundError: dev/architectury/event/Event"));
+        assert_eq!(summary.suspected_mods, vec!["Roughly Enough Items (rei), Version: 12.0.625"]);
+
+        assert_eq!(summary.mods.len(), 3);
+        let errored: Vec<&str> = summary.mods.iter().filter(|m| m.errored).map(|m| m.id.as_str()).collect();
+        assert_eq!(errored, vec!["rei"]);
+        assert!(summary.hints.iter().any(|h| h.contains("missing dependency")));
+    }
+
+    #[test]
+    fn legacy_report() {
+        let summary = parse_report(LEGACY_REPORT);
+
+        assert!(summary.exception.unwrap().starts_with("net.minecraftforge.fml.common.MissingModsException"));
+        assert!(summary.suspected_mods.is_empty());
+
+        assert_eq!(summary.mods.len(), 2);
+        assert_eq!(summary.mods[1].id, "jei");
+        assert_eq!(summary.mods[1].version, "4.16.1.301");
+        assert!(summary.mods[1].errored);
+        assert!(!summary.mods[0].errored);
+        assert!(summary.hints.iter().any(|h| h.contains("dependencies")));
+    }
+
+    #[test]
+    fn log_without_report() {
+        let log = "[main/INFO]: Loading\n\
+            Exception in thread \"main\" java.lang.UnsupportedClassVersionError: net/minecraft/client/main/Main has been compiled by a more recent version of the Java Runtime (class file version 61.0), this version of the Java Runtime only recognizes class file versions up to 52.0\n\
+            \tat java.lang.ClassLoader.defineClass1(Native Method)\n";
+
+        let summary = parse_log(log).unwrap();
+        assert!(summary.exception.unwrap().starts_with("java.lang.UnsupportedClassVersionError"));
+        assert_eq!(summary.hints, vec!["Something was built for java 17, check which java the instance uses"]);
+
+        assert!(parse_log("[main/INFO]: Stopping!\n").is_none());
+    }
+}
@@ -1,8 +1,7 @@
-//use crypto::{digest::Digest, sha1::Sha1};
+use crypto::{digest::Digest, sha1::Sha1};
 use std::fs::File;
-use std::fs::OpenOptions;
-use std::io::{Write};
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::{fs, time::Duration};
 use std::collections::HashMap;
 //use reqwest::Client;
@@ -12,7 +11,6 @@ extern crate crypto;
 use log::info;
 
 
-use reqwest::Url;
 use futures::StreamExt;
 
 #[derive(Clone)]
@@ -33,84 +31,50 @@ impl Downloader {
         }
     }
 
+    // Network and http errors are returned, the first one stops
+    // the downloads that are left
     pub async fn process(&self) -> Result<(), Box<dyn std::error::Error>> {
         let downloads = self.queue.clone();
 
-        let fetches = 
+        let mut fetches = 
             futures::stream::iter(downloads).map(|(path, url)|{
                 let client = self.client.clone();
                 tokio::spawn(async move {
-                    let resp = client.get(Url::parse(url.as_str()).unwrap()).send().await.unwrap();
-
                     info!("Downloading {}", url);
-                    (path, resp.bytes().await)
+                    let resp = client.get(url.as_str()).send().await?.error_for_status()?;
+                    let bytes = resp.bytes().await?;
+
+                    Ok::<_, reqwest::Error>((path, bytes))
                 })
             }).buffer_unordered(8);
         
-        fetches.for_each(|r| async move {
-            match r {
-                Ok((path, bytes)) => {
-                    match bytes {
-                        Ok(bytes) => {
-                            let parent = path.parent().unwrap();
-                            fs::create_dir_all(parent).expect("Couldn't create parent directories");
-
-                            if !path.exists(){
-                                File::create(path.clone())
-                                    .unwrap();
-                            }
-                            // create file
-                            let mut file = OpenOptions::new()
-                                .write(true)
-                                .read(true)
-                                .open(path.clone())
-                                .unwrap();
-
-                            file.write_all(&bytes[..]).expect("Error writing to file while downloading");
-
-                            info!("Finished download. Saved to {}",path.display());
-                        },
-                        Err(e) => panic!("{}",e)
-                    }
-                },
-                Err(e) => panic!("{}", e)
-                
-            }
-            
-            
-        }).await;
-
-        //fetches.await;
+        while let Some(result) = fetches.next().await {
+            let (path, bytes) = result??;
+
+            let parent = path.parent().unwrap();
+            fs::create_dir_all(parent)?;
+
+            // an existing file is replaced, not written over
+            let mut file = File::create(path.clone())?;
+
+            file.write_all(&bytes[..])?;
+
+            info!("Finished download. Saved to {}",path.display());
+        }
 
         Ok(())
     }
+}
 
-    // Verify file integrity
-    //pub fn verify_sha1(&self) -> Option<bool> {
-    //    let fp = self.file_path.clone().unwrap();
-    //    if self.sha1.is_none() {
-    //        return None;
-    //    }
-
-    //    let mut hasher = Sha1::new();
-    //    let mut file = OpenOptions::new()
-    //        .read(true)
-    //        .write(false)
-    //        .open(fp.clone())
-    //        .unwrap();
-    //    let mut file_data = Vec::new();
-    //    match file.read_to_end(&mut file_data) {
-    //        Ok(_) => (),
-    //        Err(e) => {
-    //            println!("{}: {}", Red.paint("Unable to read file data"), e);
-    //        }
-    //    }
-
-    //    hasher.input(&file_data);
-    //    let hex = hasher.result_str();
-
-    //    return Some(hex == self.sha1.clone().unwrap());
-    //}
+// Verify file integrity
+pub fn verify_sha1(path: &Path, sha1: &str) -> bool {
+    let mut file_data = Vec::new();
+    if File::open(path).and_then(|mut f| f.read_to_end(&mut file_data)).is_err() {
+        return false;
+    }
 
+    let mut hasher = Sha1::new();
+    hasher.input(&file_data);
 
+    hasher.result_str() == sha1.to_lowercase()
 }
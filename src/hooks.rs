@@ -0,0 +1,23 @@
+use std::error::Error;
+use std::path::Path;
+
+use subprocess::{Exec, ExitStatus};
+
+// Commands users want run around a launch, e.g. pulling configs
+// before and backing up saves after. They run through the shell in
+// the instance folder and learn about the instance from INST_*
+// environment variables.
+
+// exit code of the hook, None if it was killed by a signal
+pub fn run(command: &str, instance_path: &Path, vars: &[(String, String)]) -> Result<Option<i32>, Box<dyn Error>> {
+    let mut exec = Exec::shell(command).cwd(instance_path);
+    for (name, value) in vars {
+        exec = exec.env(name, value);
+    }
+
+    Ok(match exec.join()? {
+        ExitStatus::Exited(code) => Some(code as i32),
+        ExitStatus::Other(code) => Some(code),
+        ExitStatus::Signaled(_) | ExitStatus::Undetermined => None,
+    })
+}
@@ -1,10 +1,22 @@
 
+use std::cell::Cell;
+use std::error::Error;
 use std::fs;
 use std::fs::OpenOptions;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use crate::invoker::Invoker;
+use ansi_term::Colour::Yellow;
+
+use crate::arguments::Arguments;
+use crate::hooks;
+use crate::invoker::{self, Invoker, LaunchResult};
+use crate::java;
+use crate::profile::{self, VersionProfile};
+use crate::registry::{self, RunningInstance};
+use crate::rules::RuleContext;
+use crate::settings::{GameOptions, InstanceSettings};
+use crate::setup;
 
 #[derive(Clone)]
 pub enum InstanceType {
@@ -49,7 +61,8 @@ impl Instance {
             .expect("Invalid invoker json");
 
         let config = invoker_json["custom_args"].as_str().unwrap();
-        println!("{}", config);
+        let token = invoker_json["auth_token"].as_str().unwrap_or("");
+        println!("{}", invoker::redact(config, token));
     }
 
     pub fn name(&self) -> String {
@@ -98,14 +111,221 @@ impl Instance {
             .expect("Unable to write to sml invoker file");
     }
 
-    pub fn launch(&self, verbose: bool) {
+    pub fn settings(&self) -> InstanceSettings {
+        match &self.invoker {
+            Some(invoker) => invoker.settings().clone(),
+            None => InstanceSettings::default(),
+        }
+    }
+
+    // Change one of the typed settings, the file is only written
+    // when the settings are valid together
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let mut invoker_file_path = self.path.clone();
+        invoker_file_path.push("sml_invoker.json");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .open(invoker_file_path.clone())?;
+
+        let mut invoker_json : serde_json::Value = serde_json::from_reader(file)?;
+
+        let mut settings: InstanceSettings = serde_json::from_value(invoker_json["settings"].clone()).unwrap_or_default();
+        settings.set(key, value)?;
+        // the java that is actually configured, not the one the version asks for
+        let java_path = PathBuf::from(invoker_json["java"].as_str().unwrap_or("java"));
+        settings.validate(java::probe(&java_path).map(|install| install.major))?;
+
+        invoker_json["settings"] = serde_json::json!(settings);
+
+        std::fs::write(invoker_file_path, invoker_json.to_string())?;
+
+        Ok(())
+    }
+
+    // ids of all version jsons in the instance's versions folder
+    pub fn available_versions(&self) -> Vec<String> {
+        let mut versions_path = self.path.clone();
+        versions_path.push("versions");
+
+        let mut versions = Vec::new();
+        if let Ok(entries) = fs::read_dir(versions_path.clone()) {
+            for entry in entries.flatten() {
+                let id = entry.file_name().to_string_lossy().to_string();
+                if profile::json_path(&versions_path, id.as_str()).exists() {
+                    versions.push(id);
+                }
+            }
+        }
+
+        versions.sort();
+        versions
+    }
+
+    // Switch the instance over to another version json in its
+    // versions folder, e.g. one that was added by an installer
+    pub async fn set_version(&self, version_id: &str) -> Result<(), Box<dyn Error>> {
+        let mut versions_path = self.path.clone();
+        versions_path.push("versions");
+
+        let profile = VersionProfile::load(&versions_path, version_id)?;
+        let rules = RuleContext::current();
+
+        setup::install_version(self.path.clone(), &profile, &rules).await;
+
+        let classpaths = setup::get_classpath(self.path.clone(), &profile, &rules);
+        let arguments = Arguments::from_profile(&profile, &rules, &self.path);
+
+        let mut invoker_file_path = self.path.clone();
+        invoker_file_path.push("sml_invoker.json");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(invoker_file_path.clone())?;
+
+        let mut invoker_json : serde_json::Value = serde_json::from_reader(file)?;
+
+        invoker_json["classpaths"] = serde_json::json!(classpaths);
+        invoker_json["mainclass"] = serde_json::json!(profile.main_class().ok_or("Version has no main class")?);
+        invoker_json["jvm_args"] = serde_json::json!(arguments.jvm);
+        invoker_json["game_args"] = serde_json::json!(arguments.game);
+        invoker_json["conditional_game_args"] = serde_json::json!(arguments.conditional_game);
+        invoker_json["version_id"] = serde_json::json!(profile.id);
+        invoker_json["mc_version"] = serde_json::json!(profile.chain.last());
+        invoker_json["version_type"] = serde_json::json!(profile.json()["type"].as_str().unwrap_or("release"));
+
+        let java_required = java::required_major(profile.json());
+        invoker_json["java"] = serde_json::json!(java::java_for_profile(&profile).await);
+        invoker_json["java_required"] = serde_json::json!(java_required);
+
+        std::fs::write(invoker_file_path, invoker_json.to_string())?;
+
+        Ok(())
+    }
+
+    // what hooks get to know about the instance
+    fn hook_vars(&self, invoker: &Invoker) -> Vec<(String, String)> {
+        vec![
+            ("INST_DIR".to_string(), self.path.display().to_string()),
+            ("INST_NAME".to_string(), self.name.clone()),
+            ("INST_ID".to_string(), self.uuid()),
+            ("INST_MC_VERSION".to_string(), invoker.mc_version().to_string()),
+        ]
+    }
+
+    // Two copies of an instance write to the same worlds, so a
+    // running instance is only launched again when forced. The
+    // launch lock is held until the game's pid is registered.
+    pub fn launch(&self, verbose: bool, attach: bool, force: bool, overrides: &GameOptions) -> Result<LaunchResult, Box<dyn Error>> {
+        let launch_lock = Cell::new(None);
+        if !force {
+            launch_lock.set(Some(registry::lock_launch(&self.path)?));
+
+            if let Some(running) = registry::find(self.uuid().as_str())? {
+                return Err(format!("{} is already running (pid {}), use --force to launch it anyway", self.name, running.pid).into());
+            }
+        }
+
+        let mut invoker = self.invoker.clone().ok_or("Instance has no invoker")?;
+        invoker.override_game_options(overrides);
+
+        let settings = invoker.settings().clone();
+        let mut vars = self.hook_vars(&invoker);
 
-        match self.invoker.clone() {
-            Some(mut invoker) => {
-                invoker.invoke(self.path.clone(), verbose);
-            },
-            None => (),
+        if let Some(hook) = &settings.pre_launch {
+            match hooks::run(hook, &self.path, &vars)? {
+                Some(0) => (),
+                Some(code) => return Err(format!("Pre-launch hook exited with code {}", code).into()),
+                None => return Err("Pre-launch hook was killed".into()),
+            }
         }
+
+        // the game starts in fullscreen when it was left that way,
+        // whatever the arguments say
+        if settings.game.fullscreen == Some(false) {
+            if let Err(e) = self.leave_fullscreen() {
+                println!("{} couldn't turn off fullscreen in options.txt: {}", Yellow.paint("Warning:"), e);
+            }
+        }
+
+        // the post-exit hook needs to know when the game exits
+        let attach = attach || settings.post_exit.is_some();
+        let register = |pid: Option<u32>| {
+            if let Some(pid) = pid {
+                let running = RunningInstance::new(self.uuid(), self.name.clone(), self.path.clone(), pid);
+                if let Err(e) = registry::register(running) {
+                    println!("{} couldn't record the running instance: {}", Yellow.paint("Warning:"), e);
+                }
+            }
+            drop(launch_lock.take());
+        };
+
+        let result = invoker.invoke(self.path.clone(), verbose, attach, &register)?;
+
+        if let LaunchResult::Exited { pid: Some(pid), .. } = &result {
+            let _ = registry::unregister(*pid);
+        }
+
+        if let (Some(hook), LaunchResult::Exited { code, .. }) = (&settings.post_exit, &result) {
+            // empty if the game was killed
+            vars.push(("INST_EXIT_CODE".to_string(), code.map(|c| c.to_string()).unwrap_or_default()));
+
+            match hooks::run(hook, &self.path, &vars) {
+                Ok(Some(0)) => (),
+                Ok(Some(code)) => println!("{} post-exit hook exited with code {}", Yellow.paint("Warning:"), code),
+                Ok(None) => println!("{} post-exit hook was killed", Yellow.paint("Warning:")),
+                Err(e) => println!("{} couldn't run the post-exit hook: {}", Yellow.paint("Warning:"), e),
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn leave_fullscreen(&self) -> Result<(), Box<dyn Error>> {
+        let options_path = self.path.join("options.txt");
+        if !options_path.exists() {
+            return Ok(());
+        }
+
+        let options = fs::read_to_string(&options_path)?;
+        if !options.lines().any(|line| line == "fullscreen:true") {
+            return Ok(());
+        }
+
+        let lines: Vec<&str> = options
+            .lines()
+            .map(|line| if line == "fullscreen:true" { "fullscreen:false" } else { line })
+            .collect();
+        fs::write(&options_path, lines.join("\n") + "\n")?;
+
+        Ok(())
+    }
+
+    // what `launch` would run, hooks are only named
+    pub fn dry_run(&self, overrides: &GameOptions) -> Result<(), Box<dyn Error>> {
+        let mut invoker = self.invoker.clone().ok_or("Instance has no invoker")?;
+        invoker.override_game_options(overrides);
+        invoker.dry_run(&self.path)?;
+
+        let settings = invoker.settings();
+        if let Some(hook) = &settings.pre_launch {
+            println!("Pre-launch hook: {}", hook);
+        }
+        if let Some(hook) = &settings.post_exit {
+            println!("Post-exit hook: {}", hook);
+        }
+
+        Ok(())
+    }
+
+    // launch.sh in the instance folder unless `output` is given
+    pub fn export_script(&self, output: Option<PathBuf>, token_placeholder: bool) -> Result<PathBuf, Box<dyn Error>> {
+        let invoker = self.invoker.clone().ok_or("Instance has no invoker")?;
+        let output = output.unwrap_or_else(|| self.path.join("launch.sh"));
+
+        invoker.export_script(&self.path, &output, token_placeholder)?;
+        Ok(output)
     }
 
     pub fn get_path(&self) -> PathBuf {
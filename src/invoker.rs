@@ -1,21 +1,79 @@
+use ansi_term::Colour::Yellow;
 use serde_json::json;
-use subprocess::Redirection;
+use subprocess::{ExitStatus, NullFile, Redirection};
 use uuid::Uuid;
-use std::io::Write;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 use subprocess::Exec;
 
+use crate::arguments::{self, Arguments};
+use crate::auth::User;
+use crate::config::Config;
 use crate::instance::InstanceType;
+use crate::java;
+use crate::profile::VersionProfile;
+use crate::rules::RuleContext;
+use crate::settings::{self, GameOptions, InstanceSettings};
+
+const REDACTED: &str = "<redacted>";
+
+// what exported scripts read the access token from
+const TOKEN_VAR: &str = "SML_ACCESS_TOKEN";
+// stands in for the token until the script is written
+const TOKEN_MARK: &str = "\u{1}sml-access-token\u{1}";
+
+// launch logs kept in an instance's logs folder
+const KEPT_LAUNCH_LOGS: usize = 10;
+
+#[derive(Clone, Debug)]
+pub enum LaunchResult {
+    // the game keeps running on its own
+    Detached { pid: Option<u32> },
+    // code is None when the game was killed by a signal
+    Exited { pid: Option<u32>, started: SystemTime, code: Option<i32>, log_path: PathBuf },
+}
+
+// token of offline accounts, it isn't secret and hiding every 0
+// would garble the text
+const OFFLINE_TOKEN: &str = "0";
+
+// Hide a secret in text that gets shown to the user
+pub fn redact(text: &str, secret: &str) -> String {
+    if secret.is_empty() || secret == OFFLINE_TOKEN {
+        text.to_string()
+    } else {
+        text.replace(secret, REDACTED)
+    }
+}
 
 #[derive(Clone)]
 pub struct Invoker {
     java: String,
+    // major java version the game needs, 0 if unknown
+    java_required: u32,
+    settings: InstanceSettings,
     custom_args: Option<String>,
     binpath: PathBuf,
     classpaths: Vec<PathBuf>,
-    args: String,
+    jvm_args: Vec<String>,
+    game_args: Vec<String>,
+    // game arguments that depend on the game options
+    conditional_game_args: Vec<serde_json::Value>,
     main: String,
-    ccmd: Option<String>,
+    version_id: String,
+    // minecraft version underneath e.g. a forge version
+    mc_version: String,
+    // release, snapshot, old_beta or old_alpha
+    version_type: String,
+    // program and arguments, no shell involved
+    ccmd: Option<Vec<OsString>>,
     instance_type: InstanceType,
     instance_name: String,
     user_name: String,
@@ -24,20 +82,27 @@ pub struct Invoker {
 }
 
 impl Invoker {
-    pub fn new(java: String, binpath: PathBuf, classpaths: Vec<PathBuf>, args: String, main: String, instance_name: String, instance_type: InstanceType, user_name : String, auth_token : String, uuid: String) -> Invoker {
+    pub fn new(java: String, classpaths: Vec<PathBuf>, profile: &VersionProfile, arguments: Arguments, instance_name: String, instance_type: InstanceType, user: &User) -> Invoker {
         Invoker {
             java,
+            java_required: java::required_major(profile.json()),
+            settings: InstanceSettings::default(),
             custom_args: None,
-            binpath,
+            binpath: PathBuf::from("./bin"),
             classpaths,
-            args,
-            main,
+            jvm_args: arguments.jvm,
+            game_args: arguments.game,
+            conditional_game_args: arguments.conditional_game,
+            main: profile.main_class().expect("Version has no main class").to_string(),
+            version_id: profile.id.clone(),
+            mc_version: profile.chain.last().unwrap().clone(),
+            version_type: profile.json()["type"].as_str().unwrap_or("release").to_string(),
             ccmd: None,
             instance_type,
             instance_name,
-            user_name,
-            auth_token,
-            uuid
+            user_name: user.name.clone(),
+            auth_token: user.token.clone(),
+            uuid: user.id.clone()
         }
     }
 
@@ -45,51 +110,202 @@ impl Invoker {
         self.instance_name = new_name;
     }
 
-    pub fn gen_invocation(&mut self) {
-        let mut cmd: String = self.java.clone();
-        cmd.push_str(format!(" -Dfml.ignoreInvalidMinecraftCertificates=true  -Djava.library.path=./bin ").as_str());
+    pub fn settings(&self) -> &InstanceSettings {
+        &self.settings
+    }
 
-        match &self.custom_args {
-            Some(args) => {
-                cmd.push_str(format!(" {} ", args).as_str());
-            }
-            None => (),
-        }
-
-        // classpaths
-        cmd.push_str(" -cp ");
-        //if cfg!(windows) {
-        //    cmd.push_str("\"")
-        //}
-        
-        for cp in self.classpaths.clone() {
-            let cp_str = if cfg!(windows) {
-                format!("{};", cp.display())
-            } else {
-                format!("\"{}\":", cp.display())
-            };
-            cmd.push_str(cp_str.as_str());
+    pub fn mc_version(&self) -> &str {
+        self.mc_version.as_str()
+    }
+
+    // options for this launch only, on top of the instance's
+    pub fn override_game_options(&mut self, overrides: &GameOptions) {
+        self.settings.game = self.settings.game.with_overrides(overrides);
+        self.ccmd = None;
+    }
+
+    // placeholders that can only be filled in right before launching
+    fn launch_vars(&self, token: &str) -> HashMap<String, String> {
+        let mut vars = HashMap::new();
+
+        let classpath: Vec<String> = self.classpaths
+            .iter()
+            .map(|cp| cp.display().to_string())
+            .collect();
+
+        vars.insert("classpath".to_string(), classpath.join(arguments::classpath_separator()));
+        vars.insert("natives_directory".to_string(), self.binpath.display().to_string());
+        vars.insert("auth_player_name".to_string(), self.user_name.clone());
+        vars.insert("auth_uuid".to_string(), self.uuid.clone());
+        vars.insert("auth_access_token".to_string(), token.to_string());
+        vars.insert("auth_session".to_string(), format!("token:{}:{}", token, self.uuid));
+        vars.insert("auth_xuid".to_string(), String::new());
+        vars.insert("clientid".to_string(), String::new());
+        vars.insert("user_type".to_string(), "mojang".to_string());
+        vars.insert("user_properties".to_string(), "{}".to_string());
+
+        vars
+    }
+
+    // environment variables set for the game, the instance's own
+    // win over the global ones
+    pub fn launch_env(&self) -> BTreeMap<String, String> {
+        let mut env = Config::load().env;
+        env.retain(|name, _| settings::valid_env_name(name));
+        env.extend(self.settings.env.clone());
+        env
+    }
+
+    pub fn gen_invocation(&mut self) -> Result<(), Box<dyn Error>> {
+        self.ccmd = Some(self.build_cmd(self.auth_token.as_str())?);
+        Ok(())
+    }
+
+    // Program and arguments of the launch. The token is passed in
+    // so dry runs and scripts can use something else.
+    fn build_cmd(&self, token: &str) -> Result<Vec<OsString>, Box<dyn Error>> {
+        let vars = self.launch_vars(token);
+
+        let mut cmd: Vec<OsString> = Vec::new();
+
+        // e.g. gamemoderun prime-run java ...
+        for arg in Config::load().wrapper.iter().chain(self.settings.wrapper.iter()) {
+            cmd.push(OsString::from(arg));
+        }
+
+        cmd.push(OsString::from(self.java.trim()));
+        cmd.push(OsString::from("-Dfml.ignoreInvalidMinecraftCertificates=true"));
+
+        for arg in &self.jvm_args {
+            cmd.push(OsString::from(arguments::substitute(arg, &vars)));
         }
-        //if cfg!(windows) {
-            //cmd.push_str("\"")
-        //}
 
-        // do user info separately
-        let userinfo_string = format!(" --accessToken {} --username {} --uuid {} ", 
-                                      self.auth_token, self.user_name, self.uuid);
-        self.args.push_str(userinfo_string.as_str());
+        cmd.extend(self.settings.jvm_arguments().into_iter().map(OsString::from));
+
+        // custom args are written like on a command line
+        if let Some(args) = &self.custom_args {
+            let args = shell_words::split(args).map_err(|e| format!("Invalid custom arguments: {}", e))?;
+            cmd.extend(args.into_iter().map(OsString::from));
+        }
 
         // main class
-        cmd.push_str(format!(" {} {} ", self.main, self.args).as_str());
+        cmd.push(OsString::from(self.main.as_str()));
+
+        for arg in &self.game_args {
+            cmd.push(OsString::from(arguments::substitute(arg, &vars)));
+        }
+
+        for arg in arguments::game_option_arguments(&self.conditional_game_args, &self.settings.game)? {
+            cmd.push(OsString::from(arg));
+        }
+
+        Ok(cmd)
+    }
+
+    // everything a launch would run, without running it
+    pub fn dry_run(&self, instance_path: &Path) -> Result<(), Box<dyn Error>> {
+        self.check_java();
+
+        let cmd = self.build_cmd(REDACTED)?;
+        let token = self.auth_token.as_str();
+
+        println!("Working directory: {}", instance_path.display());
+
+        println!("Environment:");
+        for (name, value) in self.launch_env() {
+            println!("  {}={}", name, redact(&value, token));
+        }
+
+        println!("Arguments:");
+        for arg in cmd {
+            println!("  {}", redact(&arg.to_string_lossy(), token));
+        }
+
+        Ok(())
+    }
+
+    // Write a shell script that launches the game like sml does.
+    // With a token placeholder the script takes the access token
+    // from $SML_ACCESS_TOKEN instead of containing it.
+    pub fn export_script(&self, instance_path: &Path, output: &Path, token_placeholder: bool) -> Result<(), Box<dyn Error>> {
+        let token = if token_placeholder { TOKEN_MARK } else { self.auth_token.as_str() };
+        let cmd = self.build_cmd(token)?;
+
+        let mut script = String::from("#!/bin/sh\n");
+        script.push_str(&format!("# {} ({}), exported by sml\n", self.instance_name, self.version_id));
+
+        if self.settings.pre_launch.is_some() || self.settings.post_exit.is_some() {
+            script.push_str("# the instance's hooks aren't run by this script\n");
+        }
+
+        if token_placeholder {
+            script.push_str(&format!(
+                "if [ -z \"${0}\" ]; then\n    echo \"Set {0} to the access token\" >&2\n    exit 1\nfi\n",
+                TOKEN_VAR
+            ));
+        }
 
-        self.ccmd = Some(cmd);
+        script.push_str(&format!("cd {} || exit 1\n", shell_words::quote(&instance_path.display().to_string())));
+
+        for (name, value) in self.launch_env() {
+            script.push_str(&format!("export {}={}\n", name, shell_words::quote(&value)));
+        }
+
+        let args: Vec<String> = cmd.iter().map(|arg| script_arg(&arg.to_string_lossy())).collect();
+        script.push_str(&format!("exec {}\n", args.join(" \\\n    ")));
+
+        // the script may contain the token, so it is private before
+        // anything is written, an existing file included
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o700);
+        }
+
+        let mut file = options.open(output)?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o700))?;
+        }
+
+        file.write_all(script.as_bytes())?;
+
+        Ok(())
+    }
+
+    // The command quoted like for a shell, only for showing it.
+    // The access token is hidden, it only goes to the game itself.
+    fn shown_invocation(&self) -> Option<String> {
+        let cmd = self.ccmd.as_ref()?;
+
+        let mut env: Vec<String> = Vec::new();
+        for (name, value) in self.launch_env() {
+            let value = redact(&value, self.auth_token.as_str());
+            env.push(format!("{}={}", name, shell_words::quote(&value)));
+        }
+
+        let mut args: Vec<String> = Vec::new();
+        for arg in cmd {
+            let arg = arg.to_string_lossy();
+            if args.last().map(|a| a.as_str()) == Some("--accessToken") {
+                args.push(REDACTED.to_string());
+            } else {
+                args.push(redact(&arg, self.auth_token.as_str()));
+            }
+        }
+
+        env.push(shell_words::join(args));
+        Some(env.join(" "))
     }
 
     pub fn display_invocation(&self) {
-        match self.ccmd.clone() {
-            Some(cmd) => {
-                println!("{}", cmd);
-            },
+        match self.shown_invocation() {
+            Some(cmd) => println!("{}", cmd),
             None => println!("No command ") 
         }
     }
@@ -112,12 +328,19 @@ impl Invoker {
 
         //using relative bin path
         let serialized_invoker_data = json!({
-            "java":"java",
+            "java": self.java,
+            "java_required": self.java_required,
+            "settings": self.settings,
             "binpath" : "./bin",
             "custom_args": custom_args,
             "classpaths" : self.classpaths,
             "mainclass" : self.main,
-            "game_args" : self.args,
+            "jvm_args" : self.jvm_args,
+            "game_args" : self.game_args,
+            "conditional_game_args" : self.conditional_game_args,
+            "version_id" : self.version_id,
+            "mc_version" : self.mc_version,
+            "version_type" : self.version_type,
             "user_name" : self.user_name,
             "instance_name" : self.instance_name,
             "auth_token" : self.auth_token,
@@ -131,52 +354,181 @@ impl Invoker {
         file.write_all(data.as_bytes()).unwrap();
     }
 
-    pub fn get_cmd(&mut self) -> String {
+    pub fn get_cmd(&mut self) -> Result<Vec<OsString>, Box<dyn Error>> {
         match self.ccmd.clone() {
-            Some(v) => return v,
+            Some(v) => Ok(v),
             None => {
-                self.gen_invocation();
-                return self.get_cmd();
+                self.gen_invocation()?;
+                self.get_cmd()
             }
-        };
+        }
     }
 
-    pub fn invoke(&mut self, instance_path: PathBuf, verbose : bool) {
-        let mut cps = "\"".to_string();
-        for cp in &self.classpaths {
-            cps.push_str(format!("{};", cp.display()).as_str());
+    // warn when the configured java doesn't fit the version
+    fn check_java(&self) {
+        if self.java_required == 0 {
+            return;
+        }
+
+        match java::probe(Path::new(self.java.trim())) {
+            Some(install) => {
+                if !java::is_compatible(self.java_required, install.major) {
+                    println!(
+                        "{} {} is java {}, this version needs java {}",
+                        Yellow.paint("Warning:"), self.java, install.major, self.java_required
+                    );
+                }
+            }
+            None => println!("{} couldn't run java at {}", Yellow.paint("Warning:"), self.java),
         }
-        cps.push_str("\"");
+    }
+
+    // Start the game. Attached, sml waits for it to exit and its
+    // output goes to the terminal and to a log in the instance's
+    // logs folder. `on_spawn` gets the pid as soon as the game runs.
+    pub fn invoke(&mut self, instance_path: PathBuf, verbose: bool, attach: bool, on_spawn: &dyn Fn(Option<u32>)) -> Result<LaunchResult, Box<dyn Error>> {
+        self.check_java();
+
+        let cmd = self.get_cmd()?;
+
+        let mut exec = Exec::cmd(&cmd[0])
+            .args(&cmd[1..])
+            .cwd(instance_path.clone());
 
+        for (name, value) in self.launch_env() {
+            exec = exec.env(name, value);
+        }
 
-        self.gen_invocation();
-        //self.display_invocation();
-        
         if verbose {
+            self.display_invocation();
+            println!("Working directory: {}", instance_path.display());
+        }
+
+        if attach {
+            return self.run_attached(exec, &instance_path, on_spawn);
+        }
+
+        // nobody reads the output of a detached game
+        let mut process = exec
+            .stdout(NullFile)
+            .stderr(NullFile)
+            .popen()?;
+        let pid = process.pid();
+        process.detach();
+        on_spawn(pid);
+
+        Ok(LaunchResult::Detached { pid })
+    }
+
+    fn run_attached(&self, exec: Exec, instance_path: &Path, on_spawn: &dyn Fn(Option<u32>)) -> Result<LaunchResult, Box<dyn Error>> {
+        let log_path = new_launch_log(instance_path)?;
+        let mut log = File::create(&log_path)?;
+        if let Some(cmd) = self.shown_invocation() {
+            writeln!(log, "# {}", cmd)?;
+        }
+        writeln!(log, "# Working directory: {}", instance_path.display())?;
+        let log = Arc::new(Mutex::new(log));
+
+        let started = SystemTime::now();
+        let mut process = exec
+            .stdout(Redirection::Pipe)
+            .stderr(Redirection::Pipe)
+            .popen()?;
+        let pid = process.pid();
+        on_spawn(pid);
+
+        let mut readers = Vec::new();
+        if let Some(stdout) = process.stdout.take() {
+            readers.push(tee(stdout, log.clone(), self.auth_token.clone(), false));
+        }
+        if let Some(stderr) = process.stderr.take() {
+            readers.push(tee(stderr, log.clone(), self.auth_token.clone(), true));
+        }
+
+        let status = process.wait()?;
+        for reader in readers {
+            let _ = reader.join();
+        }
+
+        let code = match status {
+            ExitStatus::Exited(code) => Some(code as i32),
+            ExitStatus::Other(code) => Some(code),
+            ExitStatus::Signaled(_) | ExitStatus::Undetermined => None,
+        };
 
-            println!("{}", self.ccmd.clone().unwrap());
-            dbg!(self.ccmd.clone().unwrap()); 
-            dbg!(instance_path.clone());
-            // keep output in terminal and keep subprocess
-            Exec::shell(self.ccmd.clone().unwrap())
-                .cwd(instance_path)
-                .popen()
-                .unwrap();
-        }else{
-            Exec::shell(self.ccmd.clone().unwrap())
-                .cwd(instance_path)
-                .stdout(Redirection::Pipe)
-                .popen()
-                .unwrap()
-                .detach();
- 
-        }
-         // detach the process after launching
-
-        std::process::exit(0);
+        Ok(LaunchResult::Exited { pid, started, code, log_path })
     }
 }
 
+// quote an argument for the script, the token mark becomes a
+// reference to the token variable
+fn script_arg(arg: &str) -> String {
+    let token = format!("\"${}\"", TOKEN_VAR);
+
+    if arg.is_empty() {
+        return "''".to_string();
+    }
+
+    arg.split(TOKEN_MARK)
+        .map(|part| if part.is_empty() { String::new() } else { shell_words::quote(part).to_string() })
+        .collect::<Vec<String>>()
+        .join(token.as_str())
+}
+
+// Copy the game's output line by line to the terminal and the log.
+// Lines are copied as bytes, the game doesn't always print utf-8.
+// Logs get shared, so the token is hidden in there.
+fn tee(source: File, log: Arc<Mutex<File>>, token: String, stderr: bool) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(source).split(b'\n') {
+            let mut line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            line.push(b'\n');
+
+            let _ = if stderr {
+                io::stderr().write_all(&line)
+            } else {
+                io::stdout().write_all(&line)
+            };
+
+            if let Ok(mut log) = log.lock() {
+                let line = String::from_utf8_lossy(&line);
+                let _ = log.write_all(redact(&line, token.as_str()).as_bytes());
+            }
+        }
+    })
+}
+
+// Path for the log of a new launch, older logs beyond the last
+// few are removed
+fn new_launch_log(instance_path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let logs_path = instance_path.join("logs");
+    fs::create_dir_all(&logs_path)?;
+
+    let mut logs: Vec<PathBuf> = fs::read_dir(&logs_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| is_launch_log(path))
+        .collect();
+
+    // timestamps have the same length, so names sort by age
+    logs.sort();
+    while logs.len() >= KEPT_LAUNCH_LOGS {
+        fs::remove_file(logs.remove(0))?;
+    }
+
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    Ok(logs_path.join(format!("sml-launch-{}.log", millis)))
+}
+
+pub fn is_launch_log(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with("sml-launch-") && name.ends_with(".log"))
+}
+
 impl From<&PathBuf> for Invoker {
     fn from(fp: &PathBuf) -> Self {
         let file = std::fs::OpenOptions::new()
@@ -191,7 +543,25 @@ impl From<&PathBuf> for Invoker {
         let binpath = invoker_json["binpath"].as_str().unwrap();
         let c_paths = invoker_json["classpaths"].as_array().unwrap();
         let c_args = invoker_json["custom_args"].as_str().unwrap();
-        let game_args = invoker_json["game_args"].as_str().unwrap();
+        let jvm_args = match invoker_json["jvm_args"].as_array() {
+            Some(args) => args.iter().filter_map(|a| a.as_str()).map(|a| a.to_string()).collect(),
+            // invokers from older versions always used the same jvm args
+            None => arguments::legacy_jvm_arguments(&RuleContext::current()),
+        };
+
+        let game_args: Vec<String> = match &invoker_json["game_args"] {
+            serde_json::Value::Array(args) => args.iter().filter_map(|a| a.as_str()).map(|a| a.to_string()).collect(),
+            serde_json::Value::String(args) => {
+                // older invokers stored the game args as one string
+                // and appended the user info on launch
+                let mut args: Vec<String> = args.split_whitespace().map(|a| a.to_string()).collect();
+                for arg in &["--accessToken", "${auth_access_token}", "--username", "${auth_player_name}", "--uuid", "${auth_uuid}"] {
+                    args.push(arg.to_string());
+                }
+                args
+            },
+            _ => Vec::new(),
+        };
         let main_class = invoker_json["mainclass"].as_str().unwrap();
         let java_path = invoker_json["java"].as_str().unwrap();
         let instance_name = invoker_json["instance_name"].as_str().unwrap();
@@ -199,6 +569,7 @@ impl From<&PathBuf> for Invoker {
         let auth_token = invoker_json["auth_token"].as_str().unwrap();
         let uuid = invoker_json["uuid"].as_str().unwrap();
         let instance_type_str = invoker_json["instance_type"].as_str().unwrap();
+        let version_id = invoker_json["version_id"].as_str().unwrap_or("");
 
 
         let mut classpaths_vec: Vec<PathBuf> = Vec::new();
@@ -209,12 +580,19 @@ impl From<&PathBuf> for Invoker {
 
         Invoker {
             java: String::from(java_path),
+            java_required: invoker_json["java_required"].as_u64().unwrap_or(0) as u32,
+            settings: serde_json::from_value(invoker_json["settings"].clone()).unwrap_or_default(),
             custom_args: Some(String::from(c_args)),
             binpath: PathBuf::from(binpath),
             classpaths: classpaths_vec,
-            args: String::from(game_args),
+            jvm_args,
+            game_args,
+            conditional_game_args: invoker_json["conditional_game_args"].as_array().cloned().unwrap_or_default(),
             main: String::from(main_class),
-            ccmd: Some(String::from("")),
+            version_id: version_id.to_string(),
+            mc_version: invoker_json["mc_version"].as_str().unwrap_or(version_id).to_string(),
+            version_type: invoker_json["version_type"].as_str().unwrap_or("release").to_string(),
+            ccmd: None,
             instance_name: instance_name.to_string(),
             user_name: user_name.to_string(),
             auth_token: auth_token.to_string(),
@@ -228,3 +606,30 @@ impl From<&PathBuf> for Invoker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_arguments() {
+        assert_eq!(script_arg("-Xmx4G"), "-Xmx4G");
+        assert_eq!(script_arg("New World"), "'New World'");
+        assert_eq!(script_arg(""), "''");
+        assert_eq!(script_arg(TOKEN_MARK), "\"$SML_ACCESS_TOKEN\"");
+        assert_eq!(
+            script_arg(&format!("token:{}:some uuid", TOKEN_MARK)),
+            "token:\"$SML_ACCESS_TOKEN\"':some uuid'"
+        );
+    }
+
+    #[test]
+    fn redaction() {
+        assert_eq!(redact("--accessToken abcdefghij", "abcdefghij"), "--accessToken <redacted>");
+        // short tokens are hidden too, also inside other arguments
+        assert_eq!(redact("token:abc12:uuid", "abc12"), "token:<redacted>:uuid");
+        // the offline token isn't
+        assert_eq!(redact("-Dvalue=0", "0"), "-Dvalue=0");
+        assert_eq!(redact("-Dvalue=0", ""), "-Dvalue=0");
+    }
+}
@@ -0,0 +1,238 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use log::{info, warn};
+use serde_json::Value;
+
+use crate::profile::VersionProfile;
+use crate::rules::RuleContext;
+use crate::runtime;
+use crate::util;
+
+// Java installations and picking one that fits a version.
+//
+// Versions declare the java they were built for in
+// `javaVersion.majorVersion` (missing before 1.17, those need 8).
+// Installations are found in the usual places (runtimes sml
+// downloaded included) and identified by running `java -version`.
+// Mojang's runtime for a version is only downloaded when none of
+// them fits.
+
+#[derive(Clone, Debug)]
+pub struct JavaInstall {
+    pub path: PathBuf,
+    pub version: String,
+    pub major: u32,
+}
+
+// major java version a version json asks for
+pub fn required_major(json: &Value) -> u32 {
+    json["javaVersion"]["majorVersion"].as_u64().unwrap_or(8) as u32
+}
+
+// Old versions (and their mod loaders) break on anything newer
+// than java 8, newer ones run fine on later releases
+pub fn is_compatible(required: u32, major: u32) -> bool {
+    if required <= 8 {
+        major == 8
+    } else {
+        major >= required
+    }
+}
+
+// "1.8.0_292" -> 8, "17.0.2" -> 17, "21" -> 21
+pub fn parse_major(version: &str) -> Option<u32> {
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    let first: u32 = parts.next()?.parse().ok()?;
+
+    if first == 1 {
+        parts.next()?.parse().ok()
+    } else {
+        Some(first)
+    }
+}
+
+// run `<java> -version` and read the version it reports
+pub fn probe(java: &Path) -> Option<JavaInstall> {
+    let output = Command::new(java).arg("-version").output().ok()?;
+
+    // java prints its version to stderr
+    let text = String::from_utf8_lossy(&output.stderr).to_string();
+    let start = text.find("version \"")? + "version \"".len();
+    let len = text[start..].find('"')?;
+    let version = text[start..start + len].to_string();
+
+    Some(JavaInstall {
+        path: java.to_path_buf(),
+        major: parse_major(version.as_str())?,
+        version,
+    })
+}
+
+fn java_binary() -> &'static str {
+    if cfg!(windows) {
+        "java.exe"
+    } else {
+        "java"
+    }
+}
+
+// bin/java of every directory in `dir`
+fn jvm_dirs(dir: &Path, home: &str) -> Vec<PathBuf> {
+    let mut retvec = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let mut path = entry.path();
+            if !home.is_empty() {
+                path.push(home);
+            }
+            path.push("bin");
+            path.push(java_binary());
+            retvec.push(path);
+        }
+    }
+
+    retvec
+}
+
+// bin/java anywhere below `dir`, used for runtimes managed by sml
+fn find_binaries(dir: &Path, depth: u32) -> Vec<PathBuf> {
+    let mut retvec = Vec::new();
+
+    let java = dir.join("bin").join(java_binary());
+    if java.is_file() {
+        retvec.push(java);
+        return retvec;
+    }
+
+    if depth == 0 {
+        return retvec;
+    }
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                retvec.extend(find_binaries(&entry.path(), depth - 1));
+            }
+        }
+    }
+
+    retvec
+}
+
+// places where java usually is, most specific first
+fn candidates() -> Vec<PathBuf> {
+    let mut retvec = Vec::new();
+
+    if let Some(sml_path) = util::get_sml_path() {
+        retvec.extend(find_binaries(&sml_path.join("runtimes"), 5));
+    }
+
+    if let Ok(home) = std::env::var("JAVA_HOME") {
+        retvec.push(PathBuf::from(home).join("bin").join(java_binary()));
+    }
+
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            retvec.push(dir.join(java_binary()));
+        }
+    }
+
+    if cfg!(windows) {
+        for var in &["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Ok(program_files) = std::env::var(var) {
+                for vendor in &["Java", "Eclipse Adoptium", "Eclipse Foundation", "AdoptOpenJDK", "Zulu", "Microsoft"] {
+                    retvec.extend(jvm_dirs(&Path::new(&program_files).join(vendor), ""));
+                }
+            }
+        }
+    } else if cfg!(target_os = "macos") {
+        retvec.extend(jvm_dirs(Path::new("/Library/Java/JavaVirtualMachines"), "Contents/Home"));
+    } else {
+        retvec.extend(jvm_dirs(Path::new("/usr/lib/jvm"), ""));
+        retvec.extend(jvm_dirs(Path::new("/usr/lib64/jvm"), ""));
+    }
+
+    retvec
+}
+
+// every working java installation, without duplicates
+pub fn detect() -> Vec<JavaInstall> {
+    let mut seen: Vec<PathBuf> = Vec::new();
+    let mut retvec = Vec::new();
+
+    for path in candidates() {
+        if !path.is_file() {
+            continue;
+        }
+
+        // symlinks (e.g. /usr/bin/java) point to one of the others
+        let real = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if seen.contains(&real) {
+            continue;
+        }
+        seen.push(real);
+
+        if let Some(install) = probe(&path) {
+            retvec.push(install);
+        }
+    }
+
+    retvec
+}
+
+// The installation to use for a version that needs `required`,
+// the exact major version is preferred over newer ones
+pub fn select(required: u32) -> Option<JavaInstall> {
+    let installs = detect();
+
+    if let Some(exact) = installs.iter().find(|i| i.major == required) {
+        return Some(exact.clone());
+    }
+
+    installs
+        .into_iter()
+        .filter(|i| is_compatible(required, i.major))
+        .min_by_key(|i| i.major)
+}
+
+// path of the java to store in an instance config
+pub fn java_for(required: u32) -> String {
+    match select(required) {
+        Some(install) => {
+            info!("Using java {} at {}", install.version, install.path.display());
+            install.path.display().to_string()
+        }
+        None => {
+            warn!("No java {} installation found, using java from PATH", required);
+            "java".to_string()
+        }
+    }
+}
+
+// Java for a version: a compatible installed java, mojang's
+// runtime for it if there is none
+pub async fn java_for_profile(profile: &VersionProfile) -> String {
+    let json = profile.json();
+    java_for_component(runtime::component(json).as_str(), required_major(json)).await
+}
+
+pub async fn java_for_component(component: &str, required: u32) -> String {
+    if let Some(install) = select(required) {
+        info!("Using java {} at {}", install.version, install.path.display());
+        return install.path.display().to_string();
+    }
+
+    match runtime::install(component, &RuleContext::current()).await {
+        Ok(path) => {
+            info!("Using java runtime {} at {}", component, path.display());
+            path.display().to_string()
+        }
+        Err(e) => {
+            warn!("Unable to install java runtime {}: {}", component, e);
+            java_for(required)
+        }
+    }
+}
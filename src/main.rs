@@ -17,29 +17,44 @@ extern crate clap;
 extern crate ftp;
 
 
+pub mod arguments;
 pub mod auth;
 pub mod cf;
+pub mod config;
+pub mod crash;
 pub mod downloader;
+pub mod hooks;
 pub mod instance;
+pub mod java;
 pub mod manager;
+pub mod maven;
 pub mod invoker;
+pub mod profile;
+pub mod registry;
+pub mod rules;
+pub mod runtime;
+pub mod settings;
 pub mod setup;
 pub mod util;
+pub mod versions;
 pub mod types;
 
 
 
 use std::io::Write;
 use std::fs;
+use std::path::PathBuf;
 
 use crate::manager::InstanceManager;
 use crate::instance::Instance;
+use crate::invoker::LaunchResult;
+use crate::settings::GameOptions;
 use crate::types::{forge, vanilla};
+use crate::versions::{VersionFilter, VersionManifest, VersionType};
 
 
 use ansi_term::Colour::*;
 use clap::*;
-use env_logger;
 
 #[tokio::main]
 async fn main() {
@@ -103,6 +118,13 @@ async fn main() {
                 .help("Rename the instance with provided ID")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("set-version")
+                .long("set-version")
+                .value_name("ID")
+                .help("Switch the instance with provided ID to another version json from its versions folder")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("verbose")
                 .short("v")
@@ -110,6 +132,92 @@ async fn main() {
                 .takes_value(false)
                 .help("Shows output of launched instance"),
         )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .value_name("WIDTH")
+                .help("Window width for this launch")
+                .requires("launch")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("height")
+                .long("height")
+                .value_name("HEIGHT")
+                .help("Window height for this launch")
+                .requires("launch")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("fullscreen")
+                .long("fullscreen")
+                .help("Starts the game in fullscreen")
+                .requires("launch")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("windowed")
+                .long("windowed")
+                .help("Starts the game in a window even if the instance or its options.txt default to fullscreen")
+                .requires("launch")
+                .conflicts_with("fullscreen")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("server")
+                .long("server")
+                .value_name("ADDRESS")
+                .help("Joins a server right after starting")
+                .requires("launch")
+                .conflicts_with_all(&["world", "realm"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("port")
+                .long("port")
+                .value_name("PORT")
+                .help("Port of the server to join")
+                .requires("launch")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("world")
+                .long("world")
+                .value_name("NAME")
+                .help("Opens a singleplayer world right after starting (1.20+)")
+                .requires("launch")
+                .conflicts_with("realm")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("realm")
+                .long("realm")
+                .value_name("REALM_ID")
+                .help("Joins a realm right after starting (1.20+)")
+                .requires("launch")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Launches the instance even if it is already running")
+                .requires("launch")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Shows what launching the instance would run without running it")
+                .requires("launch")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("attach")
+                .short("w")
+                .long("attach")
+                .takes_value(false)
+                .help("Waits for the launched instance to exit and saves its output to the instance's logs folder"),
+        )
         .arg(
             Arg::with_name("add-instance")
                 .short("a")
@@ -124,6 +232,143 @@ async fn main() {
                 .help("Log in through mojang")
                 .takes_value(false),
         )
+        .subcommand(
+            SubCommand::with_name("versions")
+                .about("Lists minecraft versions")
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .help("Only list versions of a type: release, snapshot, old_beta or old_alpha")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .value_name("REGEX")
+                        .help("Only list versions whose id matches the regex")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("VERSION")
+                        .help("Only list versions released since this one")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("VERSION")
+                        .help("Only list versions released up to this one")
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("forge-versions")
+                .about("Lists forge versions for a minecraft version")
+                .arg(
+                    Arg::with_name("mc-version")
+                        .value_name("MC_VERSION")
+                        .help("Minecraft version, e.g. 1.16.5")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Shows or changes the JVM settings of an instance")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("get")
+                        .about("Shows a setting, or all of them")
+                        .arg(
+                            Arg::with_name("id")
+                                .value_name("ID")
+                                .help("Instance ID")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("key")
+                                .value_name("KEY")
+                                .help("min_heap, max_heap, gc, jvm_args or property.<name>"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Changes a setting, an empty value resets it")
+                        // values like -XX:+AlwaysPreTouch aren't flags
+                        .setting(AppSettings::AllowLeadingHyphen)
+                        .arg(
+                            Arg::with_name("id")
+                                .value_name("ID")
+                                .help("Instance ID")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("key")
+                                .value_name("KEY")
+                                .help("min_heap, max_heap, gc, jvm_args or property.<name>")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("value")
+                                .value_name("VALUE")
+                                .help("e.g. 4G, g1, zgc or \"-XX:+AlwaysPreTouch\"")
+                                .allow_hyphen_values(true)
+                                .required(true),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export-script")
+                .about("Writes a shell script that launches an instance without sml")
+                .arg(
+                    Arg::with_name("id")
+                        .value_name("ID")
+                        .help("Instance ID")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("PATH")
+                        .help("Where to write the script, launch.sh in the instance folder by default")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("token-placeholder")
+                        .long("token-placeholder")
+                        .help("Reads the access token from $SML_ACCESS_TOKEN instead of putting it in the script")
+                        .takes_value(false),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("ps")
+                .about("Lists running instances"),
+        )
+        .subcommand(
+            SubCommand::with_name("kill")
+                .about("Stops a running instance")
+                .arg(
+                    Arg::with_name("id")
+                        .value_name("ID")
+                        .help("Instance ID")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("crash-report")
+                .about("Summarizes the last crash of an instance")
+                .arg(
+                    Arg::with_name("id")
+                        .value_name("ID")
+                        .help("Instance ID")
+                        .required(true),
+                ),
+        )
         .get_matches();
 
     if app.is_present("list") {
@@ -131,108 +376,326 @@ async fn main() {
         return;
     }
 
-    // CONFIGURE
-    match app.value_of("config") {
-        Some(id) =>{
-            let instance_paths = ima.get_list();
+    // VERSIONS
+    if let Some(matches) = app.subcommand_matches("versions") {
+        let mut filter = VersionFilter::default();
+
+        if let Some(types) = matches.values_of("type") {
+            for t in types {
+                match VersionType::parse(t) {
+                    Some(version_type) => filter.types.push(version_type),
+                    None => {
+                        println!("{} {}", Red.paint("Invalid version type:"), t);
+                        return;
+                    }
+                }
+            }
+        }
+
+        if let Some(pattern) = matches.value_of("filter") {
+            match regex::Regex::new(pattern) {
+                Ok(re) => filter.pattern = Some(re),
+                Err(e) => {
+                    println!("{} {}", Red.paint("Invalid regex:"), e);
+                    return;
+                }
+            }
+        }
+
+        filter.from = matches.value_of("from").map(|v| v.to_string());
+        filter.to = matches.value_of("to").map(|v| v.to_string());
 
-            for instance_path in instance_paths {
-                let instance = Instance::from(instance_path);
-                if &instance.uuid()[0..8] == id {
-                    
-                    println!("Enter custom java flags: ");
+        let manifest = VersionManifest::fetch().await.expect("Unable to get the version manifest");
 
-                    let mut new_custom_args = String::new();
+        match manifest.filter(&filter) {
+            Ok(list) => {
+                versions::display_list(&list);
+                println!("Latest release: {}, latest snapshot: {}", manifest.latest.release, manifest.latest.snapshot);
+            }
+            Err(e) => println!("{} {}", Red.paint("Unable to list versions:"), e),
+        }
+        return;
+    }
 
-                    std::io::stdin()
-                        .read_line(&mut new_custom_args)
-                        .expect("Unable to get user input");
+    // FORGE VERSIONS
+    if let Some(matches) = app.subcommand_matches("forge-versions") {
+        let mcv = matches.value_of("mc-version").unwrap();
+        if let Err(e) = forge::display_forge_versions(mcv).await {
+            println!("{} {}", Red.paint("Unable to list forge versions:"), e);
+        }
+        return;
+    }
 
-                    let len = new_custom_args.trim_end_matches(&['\r', '\n'][..]).len();
-                    new_custom_args.truncate(len);
+    // CRASH REPORT
+    if let Some(matches) = app.subcommand_matches("crash-report") {
+        let id = matches.value_of("id").unwrap();
 
-                    instance.set_config(new_custom_args);
+        for instance_path in ima.get_list() {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                match crash::last_crash(&instance.get_path()) {
+                    Some(crash) => crash.display(),
+                    None => println!("No crashes found for {}", instance.name()),
                 }
+                return;
             }
+        }
 
-        },
-        None => ()
+        println!("{} {}", Red.paint("Instance not found: "), id);
+        return;
     }
 
-    //  SHOW CONFIG
-    match app.value_of("print-config") {
-        Some(id) => {
-            let instance_paths = ima.get_list();
-
-            for instance_path in instance_paths {
-                let instance = Instance::from(instance_path);
-                if &instance.uuid()[0..8] == id {
-                    instance.display_config(); 
+    // SETTINGS
+    if let Some(matches) = app.subcommand_matches("config") {
+        let (action, matches) = matches.subcommand();
+        let matches = matches.unwrap();
+        let id = matches.value_of("id").unwrap();
+
+        for instance_path in ima.get_list() {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                match (action, matches.value_of("key")) {
+                    ("set", Some(key)) => match instance.set_setting(key, matches.value_of("value").unwrap()) {
+                        Ok(()) => println!("{}", Green.paint("Setting changed!")),
+                        Err(e) => println!("{} {}", Red.paint("Unable to change setting:"), e),
+                    },
+                    (_, Some(key)) => match instance.settings().get(key) {
+                        Ok(value) => println!("{}", value),
+                        Err(e) => println!("{} {}", Red.paint("Unable to get setting:"), e),
+                    },
+                    (_, None) => instance.settings().display(),
                 }
+                return;
+            }
+        }
 
+        println!("{} {}", Red.paint("Instance not found: "), id);
+        return;
+    }
+
+    // RUNNING INSTANCES
+    if app.subcommand_matches("ps").is_some() {
+        match registry::load() {
+            Ok(running) if running.is_empty() => println!("No instances are running"),
+            Ok(running) => registry::display_list(&running),
+            Err(e) => println!("{} {}", Red.paint("Unable to read the running instances:"), e),
+        }
+        return;
+    }
+
+    if let Some(matches) = app.subcommand_matches("kill") {
+        let id = matches.value_of("id").unwrap();
+
+        let running = match registry::load() {
+            Ok(running) => running,
+            Err(e) => {
+                println!("{} {}", Red.paint("Unable to read the running instances:"), e);
+                return;
+            }
+        };
+
+        // a short id could match the wrong game
+        if id.len() < 8 {
+            println!("{} {}", Red.paint("Instance ID needs at least 8 characters:"), id);
+            return;
+        }
+
+        let matching: Vec<registry::RunningInstance> = running.into_iter().filter(|r| r.instance_id.starts_with(id)).collect();
+        match matching.as_slice() {
+            [running] => match registry::stop(running) {
+                Ok(()) => println!("{} {}", Green.paint("Stopped"), running.instance_name),
+                Err(e) => println!("{} {}", Red.paint("Unable to stop the instance:"), e),
+            },
+            [] => println!("{} {}", Red.paint("Instance isn't running: "), id),
+            _ => {
+                println!("{} {}", Red.paint("More than one running instance matches"), id);
+                registry::display_list(&matching);
+            }
+        }
+        return;
+    }
+
+    // EXPORT SCRIPT
+    if let Some(matches) = app.subcommand_matches("export-script") {
+        let id = matches.value_of("id").unwrap();
+
+        for instance_path in ima.get_list() {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                let output = matches.value_of("output").map(PathBuf::from);
+                let token_placeholder = matches.is_present("token-placeholder");
+
+                match instance.export_script(output, token_placeholder) {
+                    Ok(path) => {
+                        println!("{} {}", Green.paint("Launch script written to"), path.display());
+                        if !token_placeholder {
+                            println!("{} the script contains the access token, don't share it", Yellow.paint("Warning:"));
+                        }
+                    }
+                    Err(e) => println!("{} {}", Red.paint("Unable to export the launch script:"), e),
+                }
+                return;
+            }
+        }
+
+        println!("{} {}", Red.paint("Instance not found: "), id);
+        return;
+    }
+
+    // CONFIGURE
+    if let Some(id) = app.value_of("config") {
+        let instance_paths = ima.get_list();
+
+        for instance_path in instance_paths {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                
+                println!("Enter custom java flags: ");
+
+                let mut new_custom_args = String::new();
+
+                std::io::stdin()
+                    .read_line(&mut new_custom_args)
+                    .expect("Unable to get user input");
+
+                let len = new_custom_args.trim_end_matches(&['\r', '\n'][..]).len();
+                new_custom_args.truncate(len);
+
+                instance.set_config(new_custom_args);
+            }
+        }
+
+    }
+
+    //  SHOW CONFIG
+    if let Some(id) = app.value_of("print-config") {
+        let instance_paths = ima.get_list();
+
+        for instance_path in instance_paths {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                instance.display_config(); 
             }
-        },
-        None => (),
+
+        }
     };
 
     // RENAME
-    match app.value_of("rename") {
-        Some(id) => {
-            let instance_paths = ima.get_list();
+    if let Some(id) = app.value_of("rename") {
+        let instance_paths = ima.get_list();
 
-            for instance_path in instance_paths {
-                let instance = Instance::from(instance_path);
-                if &instance.uuid()[0..8] == id {
-                    let mut new_name : String = String::new();
+        for instance_path in instance_paths {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                let mut new_name : String = String::new();
 
-                    println!("Enter the new name: ");
-                    std::io::stdin()
-                        .read_line(&mut new_name)
-                        .expect("Unable to get user input");
-
-                    instance.rename(new_name);
-                }
+                println!("Enter the new name: ");
+                std::io::stdin()
+                    .read_line(&mut new_name)
+                    .expect("Unable to get user input");
 
+                instance.rename(new_name);
             }
-        },
-        None => (),
+
+        }
     };
     
-    // REMOVE 
-    match app.value_of("remove") {
-        Some(id) => {
-            let instance_paths = ima.get_list();
+    // SET VERSION
+    if let Some(id) = app.value_of("set-version") {
+        let instance_paths = ima.get_list();
+
+        for instance_path in instance_paths {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                println!("Available versions: ");
+                for version in instance.available_versions() {
+                    println!("  {}", version);
+                }
 
-            for instance_path in instance_paths {
+                let mut version_id : String = String::new();
 
-                let instance = Instance::from(instance_path);
+                println!("Enter the version: ");
+                std::io::stdin()
+                    .read_line(&mut version_id)
+                    .expect("Unable to get user input");
 
-                if &instance.uuid()[0..8] == id {
-                    instance.delete();
-                    std::process::exit(0);
+                match instance.set_version(version_id.trim()).await {
+                    Ok(()) => println!("{}", Green.paint("Version changed!")),
+                    Err(e) => println!("{} {}", Red.paint("Unable to change version:"), e),
                 }
             }
+        }
+    };
+
+    // REMOVE 
+    if let Some(id) = app.value_of("remove") {
+        let instance_paths = ima.get_list();
 
-            println!("{} {}",Red.paint("Instance not found: "), id);
-        },
-        None => (),
+        for instance_path in instance_paths {
 
+            let instance = Instance::from(instance_path);
+
+            if &instance.uuid()[0..8] == id {
+                instance.delete();
+                std::process::exit(0);
+            }
+        }
+
+        println!("{} {}",Red.paint("Instance not found: "), id);
     }
 
     // LAUNCH
-    match app.value_of("launch") {
-        Some(id) => {
-            let instance_paths = ima.get_list();
-            for instance_path in instance_paths {
-                let instance = Instance::from(instance_path);
-                if &instance.uuid()[0..8] == id {
-                    
-                    instance.launch(app.is_present("verbose"));
+    if let Some(id) = app.value_of("launch") {
+        let instance_paths = ima.get_list();
+        for instance_path in instance_paths {
+            let instance = Instance::from(instance_path);
+            if &instance.uuid()[0..8] == id {
+                // showing the output means waiting for it
+                let verbose = app.is_present("verbose");
+                let attach = verbose || app.is_present("attach");
+
+                let mut overrides = GameOptions::default();
+                for key in settings::GAME_KEYS {
+                    if let Some(value) = app.value_of(key) {
+                        if let Err(e) = overrides.set(key, value) {
+                            println!("{} {}", Red.paint("Invalid game option:"), e);
+                            return;
+                        }
+                    }
+                }
+                if app.is_present("fullscreen") {
+                    overrides.fullscreen = Some(true);
+                } else if app.is_present("windowed") {
+                    overrides.fullscreen = Some(false);
                 }
-            }
 
+                if app.is_present("dry-run") {
+                    if let Err(e) = instance.dry_run(&overrides) {
+                        println!("{} {}", Red.paint("Unable to launch:"), e);
+                    }
+                    return;
+                }
+
+                match instance.launch(verbose, attach, app.is_present("force"), &overrides) {
+                    Ok(LaunchResult::Detached { .. }) => (),
+                    Ok(LaunchResult::Exited { started, code, log_path, .. }) => {
+                        match code {
+                            Some(0) => println!("{}", Green.paint("Game exited normally")),
+                            Some(code) => println!("{} {}", Red.paint("Game exited with code"), code),
+                            None => println!("{}", Red.paint("Game was killed")),
+                        }
+                        println!("Log: {}", log_path.display());
+
+                        if let Some(crash) = crash::detect(&instance.get_path(), started, code, &log_path) {
+                            crash.display();
+                        }
+                    }
+                    Err(e) => println!("{} {}", Red.paint("Unable to launch:"), e),
+                }
+                return;
+            }
         }
-        None => (),
+
     }
 
     // AUTHENTICATION
@@ -246,34 +709,32 @@ async fn main() {
         fs::write(user_path.clone(), user_data.as_bytes()).expect("Couldn't save user info");
     }
 
-    match app.value_of("add-instance") {
+    if let Some(instance_type) = app.value_of("add-instance") {
 
-        Some(instance_type) => {
-
-            // if there is no userinfo, stop the setup process
-            if !user_path.exists() {
-                println!("{}", Red.paint("Please authenticate first!"));
-                return;
-            }
+        // if there is no userinfo, stop the setup process
+        if !user_path.exists() {
+            println!("{}", Red.paint("Please authenticate first!"));
+            return;
+        }
 
-            // instance type based logic
-            match instance_type.to_lowercase().as_str() {
-                "forge" => {
-                    println!("Curseforge project ID: ");
-                    let id = util::get_u64().unwrap();
-                    forge::setup(ima, id, user_path).await;
-                },
-                "vanilla" => {
-                    vanilla::setup(ima, user_path).await;
-                },
-                "fabric" => {
-
-                },
-                _ => {
-                    println!("{}", Red.paint("Invalid instance type"));
+        // instance type based logic
+        match instance_type.to_lowercase().as_str() {
+            "forge" => {
+                println!("Curseforge project ID (leave empty for plain forge): ");
+                match util::get_u64() {
+                    Some(id) => forge::setup(ima, id, user_path).await,
+                    None => forge::setup_plain(ima, user_path).await,
                 }
+            },
+            "vanilla" => {
+                vanilla::setup(ima, user_path).await;
+            },
+            "fabric" => {
+
+            },
+            _ => {
+                println!("{}", Red.paint("Invalid instance type"));
             }
-                    }
-        None => {}
-    };
+        }
+    }
 }
@@ -29,7 +29,7 @@ impl InstanceManager {
         // create folder if it doesn't exist
         if !ipath.exists() {
             println!("Creating instance dir at {}", ipath.display());
-            fs::create_dir(&(*ipath.as_path())).expect("Error creating instances folder");
+            fs::create_dir(ipath.as_path()).expect("Error creating instances folder");
         } else {
             println!("Directory already exists! Overwriting ...");
         }
@@ -0,0 +1,582 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+// Version comparison following maven's ComparableVersion.
+//
+// A version is split into numbers and strings on `.`, `-` and on
+// every switch between digits and letters, a `-` (or a switch)
+// starts a nested list. Numbers compare numerically, strings are
+// qualifiers ordered as
+//
+//   alpha < beta < milestone < pre < rc < snapshot < "" (release) < sp
+//
+// and unknown qualifiers come after those, in lexical order.
+// `pre` isn't known to maven, it's added here for minecraft's
+// pre-releases (1.14-pre1).
+// Trailing zeros and release qualifiers are dropped, so `1`,
+// `1.0` and `1.0.0-ga` are all the same version.
+
+const QUALIFIERS: [&str; 8] = ["alpha", "beta", "milestone", "pre", "rc", "snapshot", "", "sp"];
+
+// index of the empty qualifier in QUALIFIERS
+const RELEASE_VERSION_INDEX: &str = "6";
+
+#[derive(Clone, Debug)]
+enum Item {
+    // digits without leading zeros, "0" for zero
+    Int(String),
+    Str(String),
+    List(Vec<Item>),
+}
+
+impl Item {
+    fn int(digits: &str) -> Item {
+        let trimmed = digits.trim_start_matches('0');
+        if trimmed.is_empty() {
+            Item::Int("0".to_string())
+        } else {
+            Item::Int(trimmed.to_string())
+        }
+    }
+
+    fn string(value: &str, followed_by_digit: bool) -> Item {
+        let mut value = value;
+
+        if followed_by_digit && value.len() == 1 {
+            // a1 = alpha-1, b1 = beta-1, m1 = milestone-1
+            value = match value {
+                "a" => "alpha",
+                "b" => "beta",
+                "m" => "milestone",
+                other => other,
+            };
+        }
+
+        let value = match value {
+            "ga" | "final" | "release" => "",
+            "cr" => "rc",
+            other => other,
+        };
+
+        Item::Str(value.to_string())
+    }
+
+    fn parse(is_digit: bool, buf: &str) -> Item {
+        if is_digit {
+            Item::int(buf)
+        } else {
+            Item::string(buf, false)
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        match self {
+            Item::Int(digits) => digits == "0",
+            Item::Str(value) => comparable_qualifier(value) == RELEASE_VERSION_INDEX,
+            Item::List(items) => items.is_empty(),
+        }
+    }
+
+    // compare against a missing item (the other version is shorter)
+    fn compare_to_null(&self) -> Ordering {
+        match self {
+            Item::Int(digits) => {
+                if digits == "0" {
+                    Ordering::Equal
+                } else {
+                    Ordering::Greater
+                }
+            }
+            Item::Str(value) => comparable_qualifier(value).as_str().cmp(RELEASE_VERSION_INDEX),
+            Item::List(items) => {
+                for item in items {
+                    let result = item.compare_to_null();
+                    if result != Ordering::Equal {
+                        return result;
+                    }
+                }
+                Ordering::Equal
+            }
+        }
+    }
+
+    fn compare(&self, other: &Item) -> Ordering {
+        match (self, other) {
+            (Item::Int(a), Item::Int(b)) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+            (Item::Int(_), _) => Ordering::Greater,
+
+            (Item::Str(_), Item::Int(_)) => Ordering::Less,
+            (Item::Str(a), Item::Str(b)) => comparable_qualifier(a).cmp(&comparable_qualifier(b)),
+            (Item::Str(_), Item::List(_)) => Ordering::Less,
+
+            (Item::List(_), Item::Int(_)) => Ordering::Less,
+            (Item::List(_), Item::Str(_)) => Ordering::Greater,
+            (Item::List(a), Item::List(b)) => compare_lists(a, b),
+        }
+    }
+}
+
+fn compare_lists(left: &[Item], right: &[Item]) -> Ordering {
+    let len = std::cmp::max(left.len(), right.len());
+
+    for i in 0..len {
+        let result = match (left.get(i), right.get(i)) {
+            (Some(l), Some(r)) => l.compare(r),
+            (Some(l), None) => l.compare_to_null(),
+            (None, Some(r)) => r.compare_to_null().reverse(),
+            (None, None) => Ordering::Equal,
+        };
+
+        if result != Ordering::Equal {
+            return result;
+        }
+    }
+
+    Ordering::Equal
+}
+
+// known qualifiers sort by their position, everything
+// else comes after them in lexical order
+fn comparable_qualifier(qualifier: &str) -> String {
+    match QUALIFIERS.iter().position(|q| *q == qualifier) {
+        Some(index) => index.to_string(),
+        None => format!("{}-{}", QUALIFIERS.len(), qualifier),
+    }
+}
+
+// drop trailing zeros and release qualifiers
+fn normalize(items: &mut Vec<Item>) {
+    let mut i = items.len();
+    while i > 0 {
+        i -= 1;
+        if items[i].is_null() {
+            items.remove(i);
+        } else if !matches!(items[i], Item::List(_)) {
+            break;
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ComparableVersion {
+    value: String,
+    items: Vec<Item>,
+}
+
+impl ComparableVersion {
+    pub fn parse(version: &str) -> ComparableVersion {
+        let lower = version.to_lowercase();
+        let chars: Vec<char> = lower.chars().collect();
+
+        // every nested list is identified by the path of
+        // indexes leading to it from the top level list
+        let mut root: Vec<Item> = Vec::new();
+        let mut path: Vec<usize> = Vec::new();
+        let mut opened: Vec<Vec<usize>> = Vec::new();
+
+        fn current<'a>(root: &'a mut Vec<Item>, path: &[usize]) -> &'a mut Vec<Item> {
+            let mut list = root;
+            for index in path {
+                list = match &mut list[*index] {
+                    Item::List(items) => items,
+                    _ => unreachable!(),
+                };
+            }
+            list
+        }
+
+        // adds a new list to the current one and makes it current
+        fn open_list(root: &mut Vec<Item>, path: &mut Vec<usize>, opened: &mut Vec<Vec<usize>>) {
+            let list = current(root, path);
+            list.push(Item::List(Vec::new()));
+            path.push(list.len() - 1);
+            opened.push(path.clone());
+        }
+
+        let substring = |start: usize, end: usize| -> String { chars[start..end].iter().collect() };
+
+        let mut is_digit = false;
+        let mut start = 0;
+
+        for (i, c) in chars.iter().enumerate() {
+            if *c == '.' || *c == '-' {
+                let item = if i == start {
+                    Item::int("0")
+                } else {
+                    Item::parse(is_digit, substring(start, i).as_str())
+                };
+                current(&mut root, &path).push(item);
+                start = i + 1;
+
+                if *c == '-' {
+                    open_list(&mut root, &mut path, &mut opened);
+                }
+            } else if c.is_ascii_digit() {
+                if !is_digit && i > start {
+                    let item = Item::string(substring(start, i).as_str(), true);
+                    current(&mut root, &path).push(item);
+                    start = i;
+                    open_list(&mut root, &mut path, &mut opened);
+                }
+                is_digit = true;
+            } else {
+                if is_digit && i > start {
+                    let item = Item::parse(true, substring(start, i).as_str());
+                    current(&mut root, &path).push(item);
+                    start = i;
+                    open_list(&mut root, &mut path, &mut opened);
+                }
+                is_digit = false;
+            }
+        }
+
+        if chars.len() > start {
+            let item = Item::parse(is_digit, substring(start, chars.len()).as_str());
+            current(&mut root, &path).push(item);
+        }
+
+        // innermost lists first
+        while let Some(list_path) = opened.pop() {
+            normalize(current(&mut root, &list_path));
+        }
+        normalize(&mut root);
+
+        ComparableVersion {
+            value: version.to_string(),
+            items: root,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.value.as_str()
+    }
+}
+
+impl Ord for ComparableVersion {
+    fn cmp(&self, other: &ComparableVersion) -> Ordering {
+        compare_lists(&self.items, &other.items)
+    }
+}
+
+impl PartialOrd for ComparableVersion {
+    fn partial_cmp(&self, other: &ComparableVersion) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for ComparableVersion {
+    fn eq(&self, other: &ComparableVersion) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ComparableVersion {}
+
+impl fmt::Display for ComparableVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.value)
+    }
+}
+
+impl From<&str> for ComparableVersion {
+    fn from(version: &str) -> Self {
+        ComparableVersion::parse(version)
+    }
+}
+
+// A maven artifact as written in library names:
+//
+//   group:artifact:version[:classifier][@extension]
+//
+// e.g. `org.lwjgl:lwjgl:3.3.1:natives-linux` or
+// `de.oceanlabs.mcp:mcp_config:1.16.5-20210115.111550@zip`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MavenCoordinate {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    pub classifier: Option<String>,
+    pub extension: String,
+}
+
+impl MavenCoordinate {
+    pub fn parse(name: &str) -> Option<MavenCoordinate> {
+        let (coordinate, extension) = match name.rfind('@') {
+            Some(at) => (&name[..at], &name[at + 1..]),
+            None => (name, "jar"),
+        };
+
+        let parts: Vec<&str> = coordinate.split(':').collect();
+        if parts.len() < 3 || parts.len() > 4 || parts.iter().any(|p| p.is_empty()) || extension.is_empty() {
+            return None;
+        }
+
+        Some(MavenCoordinate {
+            group: parts[0].to_string(),
+            artifact: parts[1].to_string(),
+            version: parts[2].to_string(),
+            classifier: parts.get(3).map(|c| c.to_string()),
+            extension: extension.to_string(),
+        })
+    }
+
+    // the same artifact with another classifier
+    pub fn with_classifier(&self, classifier: &str) -> MavenCoordinate {
+        let mut coordinate = self.clone();
+        coordinate.classifier = Some(classifier.to_string());
+        coordinate
+    }
+
+    // group:artifact(:classifier), identifies a library regardless of its version
+    pub fn key(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}:{}:{}", self.group, self.artifact, classifier),
+            None => format!("{}:{}", self.group, self.artifact),
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{}.{}", self.artifact, self.version, classifier, self.extension),
+            None => format!("{}-{}.{}", self.artifact, self.version, self.extension),
+        }
+    }
+
+    // path inside a maven repository, always separated by `/`
+    pub fn path(&self) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            self.group.replace('.', "/"),
+            self.artifact,
+            self.version,
+            self.file_name()
+        )
+    }
+
+    pub fn url(&self, repository: &str) -> String {
+        format!("{}/{}", repository.trim_end_matches('/'), self.path())
+    }
+}
+
+impl fmt::Display for MavenCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{}", classifier)?;
+        }
+        if self.extension != "jar" {
+            write!(f, "@{}", self.extension)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> ComparableVersion {
+        ComparableVersion::parse(version)
+    }
+
+    // every version has to be strictly lower than all the ones after it
+    fn check_versions_order(versions: &[&str]) {
+        for i in 0..versions.len() {
+            for j in i + 1..versions.len() {
+                let low = v(versions[i]);
+                let high = v(versions[j]);
+                assert!(low < high, "expected {} < {}", versions[i], versions[j]);
+                assert!(high > low, "expected {} > {}", versions[j], versions[i]);
+            }
+        }
+    }
+
+    fn check_versions_equal(v1: &str, v2: &str) {
+        assert_eq!(v(v1).cmp(&v(v2)), Ordering::Equal, "expected {} == {}", v1, v2);
+        assert_eq!(v(v2).cmp(&v(v1)), Ordering::Equal, "expected {} == {}", v2, v1);
+    }
+
+    #[test]
+    fn qualifier_order() {
+        check_versions_order(&[
+            "1-alpha2snapshot", "1-alpha2", "1-alpha-123", "1-beta-2", "1-beta123", "1-m2", "1-m11",
+            "1-rc", "1-cr2", "1-rc123", "1-SNAPSHOT", "1", "1-sp", "1-sp2", "1-sp123", "1-abc",
+            "1-def", "1-pom-1", "1-1-snapshot", "1-1", "1-2", "1-123",
+        ]);
+    }
+
+    #[test]
+    fn number_order() {
+        check_versions_order(&[
+            "2.0", "2-1", "2.0.a", "2.0.0.a", "2.0.2", "2.0.123", "2.1.0", "2.1-a", "2.1b",
+            "2.1-c", "2.1-1", "2.1.0.1", "2.2", "2.123", "11.a2", "11.a11", "11.b2", "11.b11",
+            "11.m2", "11.m11", "11", "11.a", "11b", "11c", "11m",
+        ]);
+    }
+
+    #[test]
+    fn trailing_zeros_are_equal() {
+        check_versions_equal("1", "1");
+        check_versions_equal("1", "1.0");
+        check_versions_equal("1", "1.0.0");
+        check_versions_equal("1.0", "1.0.0");
+        check_versions_equal("1", "1-0");
+        check_versions_equal("1", "1.0-0");
+        check_versions_equal("1.0", "1.0-0");
+    }
+
+    #[test]
+    fn letters_after_numbers_start_a_qualifier() {
+        check_versions_equal("1a", "1-a");
+        check_versions_equal("1a", "1.0-a");
+        check_versions_equal("1a", "1.0.0-a");
+        check_versions_equal("1.0a", "1-a");
+        check_versions_equal("1.0.0a", "1-a");
+        check_versions_equal("1x", "1-x");
+        check_versions_equal("1x", "1.0-x");
+        check_versions_equal("1x", "1.0.0-x");
+        check_versions_equal("1.0x", "1-x");
+        check_versions_equal("1.0.0x", "1-x");
+    }
+
+    #[test]
+    fn qualifier_aliases() {
+        check_versions_equal("1ga", "1");
+        check_versions_equal("1release", "1");
+        check_versions_equal("1final", "1");
+        check_versions_equal("1cr", "1rc");
+        check_versions_equal("1a1", "1-alpha-1");
+        check_versions_equal("1b2", "1-beta-2");
+        check_versions_equal("1m3", "1-milestone-3");
+    }
+
+    #[test]
+    fn case_insensitive() {
+        check_versions_equal("1X", "1x");
+        check_versions_equal("1A", "1a");
+        check_versions_equal("1B", "1b");
+        check_versions_equal("1M", "1m");
+        check_versions_equal("1Ga", "1");
+        check_versions_equal("1GA", "1");
+        check_versions_equal("1RELEASE", "1");
+        check_versions_equal("1release", "1");
+        check_versions_equal("1RELeaSE", "1");
+        check_versions_equal("1Final", "1");
+        check_versions_equal("1FinaL", "1");
+        check_versions_equal("1FINAL", "1");
+        check_versions_equal("1Cr", "1Rc");
+        check_versions_equal("1cR", "1rC");
+        check_versions_equal("1m3", "1Milestone3");
+        check_versions_equal("1m3", "1MileStone3");
+        check_versions_equal("1m3", "1MILESTONE3");
+    }
+
+    #[test]
+    fn large_numbers() {
+        check_versions_order(&[
+            "1.0.2147483647", "1.0.2147483648", "1.0.9223372036854775807",
+            "1.0.9223372036854775808", "1.0.99999999999999999999999999999999999999",
+        ]);
+        check_versions_equal("1.0.007", "1.0.7");
+    }
+
+    #[test]
+    fn minecraft_versions() {
+        check_versions_order(&[
+            "1.7.10", "1.8", "1.8.9", "1.12", "1.12.2", "1.13", "1.13.2", "1.16.5", "1.16.10",
+            "1.17", "1.17.1", "1.18.2", "1.19", "1.20", "1.20.1", "1.20.4",
+        ]);
+        assert!(v("1.16.10") < v("1.17"));
+        assert!(v("1.16.10") > v("1.16.9"));
+        assert!(v("1.12.2") < v("1.13.2"));
+        assert!(v("1.13.2") >= v("1.13.2"));
+    }
+
+    #[test]
+    fn minecraft_pre_releases() {
+        check_versions_order(&["1.14-pre1", "1.14-pre2", "1.14-pre5", "1.14"]);
+        check_versions_order(&["1.16-pre8", "1.16-rc1", "1.16"]);
+        check_versions_order(&["1.20.1-rc1", "1.20.1", "1.20.2"]);
+        assert!(v("1.19.3") > v("1.19.3-rc3"));
+    }
+
+    #[test]
+    fn forge_versions() {
+        check_versions_order(&[
+            "14.23.5.2838", "14.23.5.2847", "14.23.5.2855", "14.23.5.2860", "36.2.39", "40.2.0",
+        ]);
+        check_versions_order(&["1.12.2-14.23.5.2847", "1.12.2-14.23.5.2855", "1.16.5-36.2.39"]);
+    }
+
+    #[test]
+    fn library_versions() {
+        check_versions_order(&["2.9.2-nightly-20140822", "2.9.4-nightly-20150209"]);
+        check_versions_order(&["3.2.1", "3.2.2", "3.3.1", "3.3.3"]);
+        check_versions_order(&["2.0-beta9", "2.0-rc1", "2.0", "2.8.1", "2.15.0", "2.16.0", "2.17.0", "2.17.1"]);
+        check_versions_order(&["1.0-SNAPSHOT", "1.0", "1.0.1-SNAPSHOT", "1.0.1"]);
+        check_versions_order(&["21.0", "31.1-jre"]);
+    }
+
+    #[test]
+    fn pre_13_check() {
+        assert!(v("1.13.2") >= v("1.13.2"));
+        assert!(v("1.16.5") >= v("1.13.2"));
+        assert!(v("1.12.2") < v("1.13.2"));
+        assert!(v("1.7.10") < v("1.13.2"));
+        assert!(v("1.13.2-pre1") < v("1.13.2"));
+    }
+
+    #[test]
+    fn display_keeps_original() {
+        assert_eq!(v("1.0-SNAPSHOT").to_string(), "1.0-SNAPSHOT");
+        assert_eq!(v("1.0-SNAPSHOT").as_str(), "1.0-SNAPSHOT");
+    }
+
+    #[test]
+    fn coordinate_parse() {
+        let c = MavenCoordinate::parse("org.lwjgl:lwjgl:3.3.1:natives-linux").unwrap();
+        assert_eq!(c.group, "org.lwjgl");
+        assert_eq!(c.artifact, "lwjgl");
+        assert_eq!(c.version, "3.3.1");
+        assert_eq!(c.classifier, Some("natives-linux".to_string()));
+        assert_eq!(c.extension, "jar");
+        assert_eq!(c.path(), "org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-linux.jar");
+
+        let c = MavenCoordinate::parse("de.oceanlabs.mcp:mcp_config:1.16.5-20210115.111550@zip").unwrap();
+        assert_eq!(c.classifier, None);
+        assert_eq!(c.extension, "zip");
+        assert_eq!(c.path(), "de/oceanlabs/mcp/mcp_config/1.16.5-20210115.111550/mcp_config-1.16.5-20210115.111550.zip");
+
+        let c = MavenCoordinate::parse("net.minecraftforge:forge:1.16.5-36.2.39:universal@jar").unwrap();
+        assert_eq!(c.file_name(), "forge-1.16.5-36.2.39-universal.jar");
+    }
+
+    #[test]
+    fn coordinate_invalid() {
+        assert_eq!(MavenCoordinate::parse("org.lwjgl:lwjgl"), None);
+        assert_eq!(MavenCoordinate::parse("a:b:c:d:e"), None);
+        assert_eq!(MavenCoordinate::parse("a::c"), None);
+        assert_eq!(MavenCoordinate::parse("a:b:c@"), None);
+    }
+
+    #[test]
+    fn coordinate_display_round_trip() {
+        for name in &[
+            "com.mojang:text2speech:1.10.3",
+            "org.lwjgl:lwjgl:3.3.1:natives-macos-arm64",
+            "de.oceanlabs.mcp:mcp_config:1.16.5-20210115.111550@zip",
+            "net.minecraftforge:forge:1.16.5-36.2.39:client@txt",
+        ] {
+            assert_eq!(MavenCoordinate::parse(name).unwrap().to_string(), *name);
+        }
+    }
+
+    #[test]
+    fn coordinate_url_and_key() {
+        let c = MavenCoordinate::parse("net.minecraft:launchwrapper:1.12").unwrap();
+        assert_eq!(c.url("https://libraries.minecraft.net/"), "https://libraries.minecraft.net/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar");
+        assert_eq!(c.url("https://maven.minecraftforge.net"), "https://maven.minecraftforge.net/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar");
+        assert_eq!(c.key(), "net.minecraft:launchwrapper");
+        assert_eq!(c.with_classifier("natives-linux").key(), "net.minecraft:launchwrapper:natives-linux");
+    }
+}
@@ -0,0 +1,173 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::maven::{ComparableVersion, MavenCoordinate};
+
+// A version json together with everything it inherits.
+//
+// Mod loaders (and other third party versions) only describe
+// what they add on top of another version and point to it with
+// `inheritsFrom`. Loading a profile follows that chain and merges
+// every parent into one json that looks like a standalone version.
+
+#[derive(Clone, Debug)]
+pub struct VersionProfile {
+    pub id: String,
+    // ids of this version and all its parents, child first
+    pub chain: Vec<String>,
+    json: Value,
+}
+
+impl VersionProfile {
+    // load `<versions_path>/<id>/<id>.json` and all of its parents
+    pub fn load(versions_path: &Path, id: &str) -> Result<VersionProfile, Box<dyn Error>> {
+        let mut chain: Vec<String> = Vec::new();
+        let mut jsons: Vec<Value> = Vec::new();
+        let mut next = Some(id.to_string());
+
+        while let Some(current) = next {
+            if chain.contains(&current) {
+                return Err(format!("Version {} inherits from itself", current).into());
+            }
+
+            let path = json_path(versions_path, current.as_str());
+            let file = File::open(&path)
+                .map_err(|e| format!("Couldn't open version json {}: {}", path.display(), e))?;
+            let json: Value = serde_json::from_reader(BufReader::new(file))?;
+
+            next = json["inheritsFrom"].as_str().map(|s| s.to_string());
+            chain.push(current);
+            jsons.push(json);
+        }
+
+        // merge from the root parent down to the requested version
+        let mut merged = jsons.pop().unwrap();
+        while let Some(child) = jsons.pop() {
+            merged = merge(merged, child);
+        }
+
+        Ok(VersionProfile {
+            id: id.to_string(),
+            chain,
+            json: merged,
+        })
+    }
+
+    pub fn json(&self) -> &Value {
+        &self.json
+    }
+
+    pub fn main_class(&self) -> Option<&str> {
+        self.json["mainClass"].as_str()
+    }
+
+    pub fn libraries(&self) -> Vec<Value> {
+        match self.json["libraries"].as_array() {
+            Some(libs) => libs.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    // id of the version whose client jar is used
+    pub fn jar_id(&self) -> String {
+        match self.json["jar"].as_str() {
+            Some(jar) => jar.to_string(),
+            None => self.chain.last().unwrap().clone(),
+        }
+    }
+}
+
+pub fn json_path(versions_path: &Path, id: &str) -> PathBuf {
+    let mut path = versions_path.to_path_buf();
+    path.push(id);
+    path.push(format!("{}.json", id));
+    path
+}
+
+// merge a child version into its parent
+fn merge(parent: Value, child: Value) -> Value {
+    let mut merged: Map<String, Value> = match parent {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+
+    let child = match child {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+
+    for (key, value) in child {
+        match key.as_str() {
+            "inheritsFrom" => (),
+            "libraries" => {
+                let parent_libs = merged.remove("libraries").unwrap_or(Value::Null);
+                merged.insert(key, Value::Array(merge_libraries(&parent_libs, &value)));
+            }
+            "arguments" => {
+                let mut arguments = match merged.remove("arguments") {
+                    Some(Value::Object(map)) => map,
+                    _ => Map::new(),
+                };
+
+                for kind in &["game", "jvm"] {
+                    let mut args = match arguments.remove(*kind) {
+                        Some(Value::Array(args)) => args,
+                        _ => Vec::new(),
+                    };
+                    if let Some(child_args) = value[*kind].as_array() {
+                        args.extend(child_args.iter().cloned());
+                    }
+                    arguments.insert(kind.to_string(), Value::Array(args));
+                }
+
+                merged.insert(key, Value::Object(arguments));
+            }
+            _ => {
+                merged.insert(key, value);
+            }
+        }
+    }
+
+    Value::Object(merged)
+}
+
+// Child libraries come first. When both declare the same library
+// (same group, artifact and classifier) the newer one is kept.
+fn merge_libraries(parent: &Value, child: &Value) -> Vec<Value> {
+    let mut libraries: Vec<Value> = match child.as_array() {
+        Some(libs) => libs.clone(),
+        None => Vec::new(),
+    };
+    let child_count = libraries.len();
+
+    if let Some(parent_libs) = parent.as_array() {
+        for lib in parent_libs {
+            let coordinate = library_coordinate(lib);
+            let duplicate = coordinate.as_ref().and_then(|c| {
+                libraries[..child_count]
+                    .iter()
+                    .position(|l| library_coordinate(l).map(|lc| lc.key()) == Some(c.key()))
+            });
+
+            match (duplicate, coordinate) {
+                (Some(index), Some(coordinate)) => {
+                    let child_version = library_coordinate(&libraries[index]).unwrap().version;
+                    if ComparableVersion::parse(&coordinate.version) > ComparableVersion::parse(&child_version) {
+                        libraries[index] = lib.clone();
+                    }
+                }
+                _ => libraries.push(lib.clone()),
+            }
+        }
+    }
+
+    libraries
+}
+
+fn library_coordinate(lib: &Value) -> Option<MavenCoordinate> {
+    MavenCoordinate::parse(lib["name"].as_str()?)
+}
@@ -0,0 +1,215 @@
+use std::error::Error;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ansi_term::Colour::Yellow;
+use prettytable::Table;
+use serde::{Deserialize, Serialize};
+
+use crate::crash;
+use crate::util;
+
+// Instances that are running, kept in running.json in the sml
+// directory so every sml process knows about them. Entries are
+// added when a game starts and dropped once its process is gone,
+// no matter whether sml saw it exit.
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunningInstance {
+    pub instance_id: String,
+    pub instance_name: String,
+    pub instance_path: PathBuf,
+    pub pid: u32,
+    // seconds since the unix epoch
+    pub started: u64,
+}
+
+impl RunningInstance {
+    pub fn new(instance_id: String, instance_name: String, instance_path: PathBuf, pid: u32) -> RunningInstance {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        RunningInstance { instance_id, instance_name, instance_path, pid, started }
+    }
+
+    pub fn is_running(&self) -> bool {
+        is_alive(self.pid, &self.instance_path)
+    }
+
+    // e.g. 1h 05m
+    pub fn uptime(&self) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let minutes = now.saturating_sub(self.started) / 60;
+
+        if minutes < 60 {
+            format!("{}m", minutes)
+        } else {
+            format!("{}h {:02}m", minutes / 60, minutes % 60)
+        }
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    util::get_sml_path().map(|mut pb| {
+        pb.push("running.json");
+        pb
+    })
+}
+
+// Every read-modify-write of running.json happens while holding
+// this, the lock goes away with the file
+fn lock() -> Result<File, Box<dyn Error>> {
+    let mut path = util::get_sml_path().ok_or("Couldn't find the sml directory")?;
+    path.push("running.lock");
+
+    let file = OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+// Held from checking whether an instance runs until its pid is
+// registered, so two launches of it can't both get through
+pub fn lock_launch(instance_path: &Path) -> Result<File, Box<dyn Error>> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(instance_path.join("sml_launch.lock"))?;
+
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => Err("The instance is already being launched".into()),
+        Err(TryLockError::Error(e)) => Err(e.into()),
+    }
+}
+
+fn read() -> Vec<RunningInstance> {
+    path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|data| serde_json::from_str(data.as_str()).ok())
+        .unwrap_or_default()
+}
+
+fn write(running: &[RunningInstance]) -> Result<(), Box<dyn Error>> {
+    let path = path().ok_or("Couldn't find the sml directory")?;
+    fs::write(path, serde_json::to_string_pretty(running)?)?;
+    Ok(())
+}
+
+// Running instances, entries of exited games are cleaned up.
+// Nobody waited for those, so a crash report they left is
+// pointed out here.
+pub fn load() -> Result<Vec<RunningInstance>, Box<dyn Error>> {
+    let _lock = lock()?;
+
+    let (running, exited): (Vec<RunningInstance>, Vec<RunningInstance>) =
+        read().into_iter().partition(|r| r.is_running());
+    if !exited.is_empty() {
+        write(&running)?;
+    }
+
+    for instance in exited {
+        let started = UNIX_EPOCH + Duration::from_secs(instance.started);
+        if crash::report_since(&instance.instance_path, started).is_some() {
+            let id = instance.instance_id.get(0..8).unwrap_or(instance.instance_id.as_str());
+            println!(
+                "{} {} crashed after it was launched, `sml crash-report {}` shows what happened",
+                Yellow.paint("Warning:"), instance.instance_name, id
+            );
+        }
+    }
+
+    Ok(running)
+}
+
+pub fn find(instance_id: &str) -> Result<Option<RunningInstance>, Box<dyn Error>> {
+    Ok(load()?.into_iter().find(|r| r.instance_id == instance_id))
+}
+
+pub fn register(instance: RunningInstance) -> Result<(), Box<dyn Error>> {
+    let _lock = lock()?;
+    let mut running: Vec<RunningInstance> = read().into_iter().filter(|r| r.is_running()).collect();
+    running.retain(|r| r.pid != instance.pid);
+    running.push(instance);
+    write(&running)
+}
+
+pub fn unregister(pid: u32) -> Result<(), Box<dyn Error>> {
+    let _lock = lock()?;
+    let mut running = read();
+    running.retain(|r| r.pid != pid);
+    write(&running)
+}
+
+// Ask the game to quit, it gets to save its worlds
+pub fn stop(instance: &RunningInstance) -> Result<(), Box<dyn Error>> {
+    let status = if cfg!(windows) {
+        Command::new("taskkill").args(["/PID", instance.pid.to_string().as_str()]).status()?
+    } else {
+        Command::new("kill").arg(instance.pid.to_string()).status()?
+    };
+
+    if !status.success() {
+        return Err(format!("Couldn't stop process {}", instance.pid).into());
+    }
+
+    unregister(instance.pid)
+}
+
+// Whether the process is still there. On linux the working
+// directory tells if the pid was reused by something else.
+fn is_alive(pid: u32, instance_path: &Path) -> bool {
+    if cfg!(target_os = "linux") {
+        let proc_path = PathBuf::from(format!("/proc/{}", pid));
+        if !proc_path.exists() {
+            return false;
+        }
+
+        // an exited game stays a zombie until something reaps it,
+        // e.g. "1234 (java) Z 1 ..."
+        if let Ok(stat) = fs::read_to_string(proc_path.join("stat")) {
+            if stat.rsplit(')').next().is_some_and(|rest| rest.trim_start().starts_with('Z')) {
+                return false;
+            }
+        }
+
+        return match fs::read_link(proc_path.join("cwd")) {
+            Ok(cwd) => cwd == instance_path || fs::canonicalize(instance_path).is_ok_and(|p| p == cwd),
+            // processes of other users can't be looked into
+            Err(_) => true,
+        };
+    }
+
+    if cfg!(windows) {
+        return Command::new("tasklist")
+            .args(["/NH", "/FI", format!("PID eq {}", pid).as_str()])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).contains(pid.to_string().as_str()))
+            .unwrap_or(false);
+    }
+
+    Command::new("kill")
+        .args(["-0", pid.to_string().as_str()])
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+pub fn display_list(running: &[RunningInstance]) {
+    let mut table = Table::new();
+    table.add_row(row!("ID", "NAME", "PID", "RUNNING FOR"));
+
+    for instance in running {
+        let id = instance.instance_id.get(0..8).unwrap_or(instance.instance_id.as_str());
+        table.add_row(row!(id, instance.instance_name, instance.pid, instance.uptime()));
+    }
+
+    table.printstd();
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+
+use regex::Regex;
+use serde_json::Value;
+
+// Evaluates the `rules` arrays found on libraries and
+// arguments in version json files.
+//
+// A rule list starts out as "disallow", every rule whose
+// conditions match the current environment overrides the
+// result with its own action. An entry without rules is
+// always allowed.
+
+#[derive(Clone, Debug)]
+pub struct RuleContext {
+    pub os_name: String,
+    pub os_arch: String,
+    pub os_version: String,
+    pub features: HashMap<String, bool>,
+}
+
+impl RuleContext {
+    pub fn new(os_name: &str, os_arch: &str, os_version: &str) -> RuleContext {
+        RuleContext {
+            os_name: os_name.to_string(),
+            os_arch: os_arch.to_string(),
+            os_version: os_version.to_string(),
+            features: HashMap::new(),
+        }
+    }
+
+    // context describing the machine sml is running on
+    pub fn current() -> RuleContext {
+        RuleContext::new(current_os_name(), current_arch(), current_os_version().as_str())
+    }
+
+    pub fn with_feature(mut self, name: &str, enabled: bool) -> RuleContext {
+        self.features.insert(name.to_string(), enabled);
+        self
+    }
+
+    // value of `${arch}` in natives classifiers
+    pub fn arch_bits(&self) -> &'static str {
+        if arch_matches("x86", self.os_arch.as_str()) || self.os_arch == "arm32" {
+            "32"
+        } else {
+            "64"
+        }
+    }
+
+    pub fn allows(&self, rules: &Value) -> bool {
+        let rules = match rules.as_array() {
+            Some(val) => val,
+            None => return true,
+        };
+
+        let mut allowed = false;
+        for rule in rules {
+            if self.matches(rule) {
+                allowed = rule["action"].as_str() == Some("allow");
+            }
+        }
+
+        allowed
+    }
+
+    // checks whether all conditions of a single rule apply
+    fn matches(&self, rule: &Value) -> bool {
+        let os = &rule["os"];
+
+        if let Some(name) = os["name"].as_str() {
+            if name != self.os_name {
+                return false;
+            }
+        }
+
+        if let Some(arch) = os["arch"].as_str() {
+            if !arch_matches(arch, self.os_arch.as_str()) {
+                return false;
+            }
+        }
+
+        if let Some(version) = os["version"].as_str() {
+            match Regex::new(version) {
+                Ok(re) => {
+                    if !re.is_match(self.os_version.as_str()) {
+                        return false;
+                    }
+                }
+                Err(_) => return false,
+            }
+        }
+
+        if let Some(features) = rule["features"].as_object() {
+            for (name, wanted) in features {
+                let enabled = self.features.get(name).copied().unwrap_or(false);
+                if wanted.as_bool() != Some(enabled) {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+}
+
+// os names as used by mojang
+pub fn current_os_name() -> &'static str {
+    match std::env::consts::OS {
+        "windows" => "windows",
+        "macos" => "osx",
+        "linux" => "linux",
+        other => other,
+    }
+}
+
+pub fn current_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" => "x86",
+        "x86_64" => "x86_64",
+        "aarch64" => "arm64",
+        "arm" => "arm32",
+        other => other,
+    }
+}
+
+// Mojang uses java's `os.arch` names, so the same cpu can
+// show up under a few different aliases
+pub fn arch_matches(rule_arch: &str, arch: &str) -> bool {
+    let normalize = |a: &str| -> String {
+        match a {
+            "amd64" | "x64" | "x86_64" => "x86_64".to_string(),
+            "i386" | "i686" | "x86" => "x86".to_string(),
+            "aarch64" | "arm64" => "arm64".to_string(),
+            other => other.to_string(),
+        }
+    };
+
+    normalize(rule_arch) == normalize(arch)
+}
+
+pub fn current_os_version() -> String {
+    if cfg!(windows) {
+        // `ver` prints something like
+        // "Microsoft Windows [Version 10.0.19045.2965]"
+        let out = Command::new("cmd").args(["/C", "ver"]).output();
+        if let Ok(out) = out {
+            let text = String::from_utf8_lossy(&out.stdout).to_string();
+            if let Some(start) = text.find("Version ") {
+                let version: Vec<&str> = text[start + 8..]
+                    .trim_end_matches(|c: char| c == ']' || c.is_whitespace())
+                    .split('.')
+                    .collect();
+                if version.len() >= 2 {
+                    return format!("{}.{}", version[0], version[1]);
+                }
+            }
+        }
+        String::new()
+    } else if cfg!(target_os = "macos") {
+        match Command::new("sw_vers").arg("-productVersion").output() {
+            Ok(out) => String::from_utf8_lossy(&out.stdout).trim().to_string(),
+            Err(_) => String::new(),
+        }
+    } else {
+        match fs::read_to_string("/proc/sys/kernel/osrelease") {
+            Ok(val) => val.trim().to_string(),
+            Err(_) => String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn os_rules() {
+        let windows = RuleContext::new("windows", "x86_64", "10.0");
+        let linux = RuleContext::new("linux", "x86_64", "6.1.0");
+
+        // no rules at all, or an allow without conditions
+        assert!(linux.allows(&Value::Null));
+        assert!(linux.allows(&json!([{ "action": "allow" }])));
+
+        // nothing matching leaves the entry disallowed
+        let only_windows = json!([{ "action": "allow", "os": { "name": "windows" } }]);
+        assert!(windows.allows(&only_windows));
+        assert!(!linux.allows(&only_windows));
+
+        // the old lwjgl style, everything except osx
+        let no_osx = json!([
+            { "action": "allow" },
+            { "action": "disallow", "os": { "name": "osx" } }
+        ]);
+        assert!(linux.allows(&no_osx));
+        assert!(!RuleContext::new("osx", "arm64", "13.4").allows(&no_osx));
+    }
+
+    #[test]
+    fn os_version_regex() {
+        let rules = json!([{ "action": "allow", "os": { "name": "windows", "version": "^10\\." } }]);
+        assert!(RuleContext::new("windows", "x86_64", "10.0").allows(&rules));
+        assert!(!RuleContext::new("windows", "x86_64", "6.1").allows(&rules));
+
+        // a broken regex never matches
+        let broken = json!([{ "action": "allow", "os": { "version": "^10\\.(" } }]);
+        assert!(!RuleContext::new("windows", "x86_64", "10.0").allows(&broken));
+    }
+
+    #[test]
+    fn arch_aliases() {
+        assert!(arch_matches("x86", "i686"));
+        assert!(arch_matches("amd64", "x86_64"));
+        assert!(arch_matches("aarch64", "arm64"));
+        assert!(!arch_matches("x86", "x86_64"));
+
+        let rules = json!([{ "action": "allow", "os": { "arch": "x86" } }]);
+        assert!(RuleContext::new("windows", "i386", "10.0").allows(&rules));
+        assert!(!RuleContext::new("windows", "amd64", "10.0").allows(&rules));
+
+        assert_eq!(RuleContext::new("windows", "i686", "10.0").arch_bits(), "32");
+        assert_eq!(RuleContext::new("linux", "aarch64", "6.1.0").arch_bits(), "64");
+    }
+
+    #[test]
+    fn features() {
+        let rules = json!([{ "action": "allow", "features": { "has_custom_resolution": true } }]);
+        let context = RuleContext::new("linux", "x86_64", "6.1.0");
+
+        assert!(!context.allows(&rules));
+        assert!(context.clone().with_feature("has_custom_resolution", true).allows(&rules));
+        assert!(!context.with_feature("has_custom_resolution", false).allows(&rules));
+
+        // asking for a disabled feature matches when it isn't set
+        let demo_off = json!([{ "action": "allow", "features": { "is_demo_user": false } }]);
+        assert!(RuleContext::new("linux", "x86_64", "6.1.0").allows(&demo_off));
+    }
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let rules = json!([
+            { "action": "allow" },
+            { "action": "disallow", "os": { "name": "linux" } },
+            { "action": "allow", "os": { "name": "linux", "arch": "arm64" } }
+        ]);
+
+        assert!(RuleContext::new("windows", "x86_64", "10.0").allows(&rules));
+        assert!(!RuleContext::new("linux", "x86_64", "6.1.0").allows(&rules));
+        assert!(RuleContext::new("linux", "aarch64", "6.1.0").allows(&rules));
+    }
+}
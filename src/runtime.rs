@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use log::info;
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::downloader::{self, Downloader};
+use crate::rules::{self, RuleContext};
+use crate::util;
+
+// Java runtimes provided by mojang.
+//
+// The runtime manifest (all.json) lists, per platform, the
+// components that are available (jre-legacy, java-runtime-gamma,
+// ...). Every component has its own manifest with the files,
+// directories and links that make up the runtime.
+//
+// Runtimes are shared by all instances and live in
+// `<sml>/runtimes/<component>/<platform>`.
+
+// component a version json asks for, old versions run on jre-legacy
+pub fn component(json: &Value) -> String {
+    json["javaVersion"]["component"]
+        .as_str()
+        .unwrap_or("jre-legacy")
+        .to_string()
+}
+
+// platform name used in the runtime manifest
+pub fn platform(rules: &RuleContext) -> Option<&'static str> {
+    let arch = rules.os_arch.as_str();
+
+    match rules.os_name.as_str() {
+        "linux" if rules::arch_matches("x86_64", arch) => Some("linux"),
+        "linux" if rules::arch_matches("x86", arch) => Some("linux-i386"),
+        "osx" if rules::arch_matches("arm64", arch) => Some("mac-os-arm64"),
+        "osx" => Some("mac-os"),
+        "windows" if rules::arch_matches("x86_64", arch) => Some("windows-x64"),
+        "windows" if rules::arch_matches("x86", arch) => Some("windows-x86"),
+        "windows" if rules::arch_matches("arm64", arch) => Some("windows-arm64"),
+        _ => None,
+    }
+}
+
+pub fn runtime_path(component: &str, platform: &str) -> Option<PathBuf> {
+    util::get_sml_path().map(|mut pb| {
+        pb.push("runtimes");
+        pb.push(component);
+        pb.push(platform);
+        pb
+    })
+}
+
+// java binary inside an installed runtime
+pub fn java_path(runtime_path: &Path) -> PathBuf {
+    let binary = if cfg!(windows) { "java.exe" } else { "java" };
+
+    // macOS runtimes come as a bundle
+    let bundle = runtime_path.join("jre.bundle/Contents/Home");
+    if bundle.exists() {
+        bundle.join("bin").join(binary)
+    } else {
+        runtime_path.join("bin").join(binary)
+    }
+}
+
+// Names and link targets come from a remote manifest, nothing
+// they point at may be outside the runtime directory
+fn runtime_file(runtime_path: &Path, name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let relative = Path::new(name);
+    if !relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir)) {
+        return Err(format!("Invalid file name {} in runtime manifest", name).into());
+    }
+
+    let path = runtime_path.join(relative);
+    if !path.starts_with(runtime_path) {
+        return Err(format!("Invalid file name {} in runtime manifest", name).into());
+    }
+
+    Ok(path)
+}
+
+// links are relative to their own folder, e.g. ../lib/libjli.so
+fn check_link_target(runtime_path: &Path, link: &Path, target: &str) -> Result<(), Box<dyn Error>> {
+    let mut resolved = link.parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for component in Path::new(target).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => (),
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            _ => return Err(format!("Invalid link target {} in runtime manifest", target).into()),
+        }
+    }
+
+    if !resolved.starts_with(runtime_path) {
+        return Err(format!("Invalid link target {} in runtime manifest", target).into());
+    }
+
+    Ok(())
+}
+
+async fn get_json(client: &reqwest::Client, url: &str) -> Result<Value, Box<dyn Error>> {
+    let resp = client.get(reqwest::Url::parse(url)?).send().await?;
+    Ok(serde_json::from_str(resp.text().await?.as_str())?)
+}
+
+// Download (or repair) a runtime component and return the path
+// of its java binary. Files that are already there with the
+// right hash are kept.
+pub async fn install(component: &str, rules: &RuleContext) -> Result<PathBuf, Box<dyn Error>> {
+    let platform = platform(rules).ok_or("No java runtimes for this platform")?;
+    let runtime_path = runtime_path(component, platform).ok_or("Couldn't find the sml directory")?;
+
+    let config = Config::load();
+    let client = reqwest::Client::new();
+
+    let all = get_json(&client, config.runtime_manifest_url.as_str()).await?;
+    let manifest_url = all[platform][component][0]["manifest"]["url"]
+        .as_str()
+        .ok_or(format!("Runtime {} isn't available for {}", component, platform))?;
+
+    let manifest = get_json(&client, manifest_url).await?;
+    let files = manifest["files"].as_object().ok_or("Invalid runtime manifest")?;
+
+    let mut downloads: HashMap<PathBuf, String> = HashMap::new();
+    let mut hashes: Vec<(PathBuf, String)> = Vec::new();
+    let mut executables: Vec<PathBuf> = Vec::new();
+    let mut links: Vec<(PathBuf, String)> = Vec::new();
+
+    for (name, file) in files {
+        let path = runtime_file(&runtime_path, name)?;
+
+        match file["type"].as_str() {
+            Some("directory") => fs::create_dir_all(&path)?,
+            Some("file") => {
+                let raw = &file["downloads"]["raw"];
+                let url = raw["url"].as_str().ok_or(format!("No download for {}", name))?;
+                let sha1 = raw["sha1"].as_str().ok_or(format!("No sha1 for {}", name))?;
+
+                if !path.exists() || !downloader::verify_sha1(&path, sha1) {
+                    downloads.insert(path.clone(), url.to_string());
+                }
+
+                if file["executable"].as_bool().unwrap_or(false) {
+                    executables.push(path.clone());
+                }
+                hashes.push((path, sha1.to_string()));
+            }
+            Some("link") => {
+                if let Some(target) = file["target"].as_str() {
+                    check_link_target(&runtime_path, &path, target)?;
+                    links.push((path, target.to_string()));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    if !downloads.is_empty() {
+        info!("Downloading java runtime {} ({} files)", component, downloads.len());
+        Downloader::new(downloads).process().await?;
+    }
+
+    for (path, sha1) in hashes {
+        if !downloader::verify_sha1(&path, sha1.as_str()) {
+            return Err(format!("Hash mismatch for {}", path.display()).into());
+        }
+    }
+
+    set_executable(&executables)?;
+    create_links(&links)?;
+
+    Ok(java_path(&runtime_path))
+}
+
+#[cfg(unix)]
+fn set_executable(paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    for path in paths {
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_mode(permissions.mode() | 0o755);
+        fs::set_permissions(path, permissions)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_executable(_paths: &[PathBuf]) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn create_links(links: &[(PathBuf, String)]) -> Result<(), Box<dyn Error>> {
+    for (path, target) in links {
+        if fs::symlink_metadata(path).is_ok() {
+            fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        std::os::unix::fs::symlink(target, path)?;
+    }
+
+    Ok(())
+}
+
+// mojang's windows runtimes don't contain links
+#[cfg(not(unix))]
+fn create_links(_links: &[(PathBuf, String)]) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_paths() {
+        let root = Path::new("/sml/runtimes/jre-legacy/linux");
+
+        assert_eq!(runtime_file(root, "bin/java").unwrap(), root.join("bin/java"));
+        assert!(runtime_file(root, "../../.bashrc").is_err());
+        assert!(runtime_file(root, "bin/../../x").is_err());
+        assert!(runtime_file(root, "/etc/passwd").is_err());
+
+        let link = root.join("bin/libjli.so");
+        assert!(check_link_target(root, &link, "../lib/libjli.so").is_ok());
+        assert!(check_link_target(root, &link, "../../../x").is_err());
+        assert!(check_link_target(root, &link, "/usr/lib/libjli.so").is_err());
+    }
+}
@@ -0,0 +1,567 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+
+use ansi_term::Colour::Yellow;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// Typed JVM settings of an instance, stored under "settings" in its
+// sml_invoker.json. They go on the command line before the
+// free-form custom args, so those still have the last word.
+
+pub const KEYS: &[&str] = &[
+    "min_heap", "max_heap", "gc", "jvm_args", "property.<name>", "wrapper", "env.<name>", "pre_launch", "post_exit",
+    "width", "height", "fullscreen", "server", "port", "world", "realm",
+];
+
+// launch options of the game itself
+pub const GAME_KEYS: &[&str] = &["width", "height", "fullscreen", "server", "port", "world", "realm"];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GcPreset {
+    // whatever the jvm picks
+    #[default]
+    Default,
+    G1,
+    Zgc,
+}
+
+impl GcPreset {
+    pub fn parse(s: &str) -> Option<GcPreset> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "default" => Some(GcPreset::Default),
+            "g1" => Some(GcPreset::G1),
+            "zgc" | "z" => Some(GcPreset::Zgc),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GcPreset::Default => "default",
+            GcPreset::G1 => "g1",
+            GcPreset::Zgc => "zgc",
+        }
+    }
+
+    pub fn arguments(&self) -> Vec<&'static str> {
+        match self {
+            GcPreset::Default => Vec::new(),
+            // G1 tuned for short pauses, the usual flags for modded servers and clients
+            GcPreset::G1 => vec![
+                "-XX:+UseG1GC",
+                "-XX:+ParallelRefProcEnabled",
+                "-XX:MaxGCPauseMillis=200",
+                "-XX:+UnlockExperimentalVMOptions",
+                "-XX:+DisableExplicitGC",
+                "-XX:G1NewSizePercent=30",
+                "-XX:G1MaxNewSizePercent=40",
+                "-XX:G1HeapRegionSize=8M",
+                "-XX:G1ReservePercent=20",
+                "-XX:G1HeapWastePercent=5",
+                "-XX:G1MixedGCCountTarget=4",
+                "-XX:InitiatingHeapOccupancyPercent=15",
+                "-XX:G1MixedGCLiveThresholdPercent=90",
+                "-XX:G1RSetUpdatingPauseTimePercent=5",
+                "-XX:SurvivorRatio=32",
+                "-XX:+PerfDisableSharedMem",
+                "-XX:MaxTenuringThreshold=1",
+            ],
+            GcPreset::Zgc => vec!["-XX:+UseZGC"],
+        }
+    }
+
+    // ZGC is experimental before java 15
+    fn min_java(&self) -> u32 {
+        match self {
+            GcPreset::Zgc => 15,
+            _ => 0,
+        }
+    }
+}
+
+// Window and quick play options. The server, world and realm are
+// where the game goes right after starting, only one of them can
+// be set.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    // name of a singleplayer world
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub world: Option<String>,
+    // realm id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+}
+
+impl GameOptions {
+    pub fn get(&self, key: &str) -> Option<String> {
+        let number = |n: &Option<u32>| n.map(|n| n.to_string()).unwrap_or_default();
+
+        match key {
+            "width" => Some(number(&self.width)),
+            "height" => Some(number(&self.height)),
+            "fullscreen" => Some(self.fullscreen.map(|f| f.to_string()).unwrap_or_default()),
+            "server" => Some(self.server.clone().unwrap_or_default()),
+            "port" => Some(self.port.map(|p| p.to_string()).unwrap_or_default()),
+            "world" => Some(self.world.clone().unwrap_or_default()),
+            "realm" => Some(self.realm.clone().unwrap_or_default()),
+            _ => None,
+        }
+    }
+
+    // an empty value resets the option
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+
+        let text = || Some(value.to_string()).filter(|v| !v.is_empty());
+        let size = || -> Result<Option<u32>, String> {
+            match value {
+                "" => Ok(None),
+                _ => match value.parse::<u32>() {
+                    Ok(size) if size > 0 => Ok(Some(size)),
+                    _ => Err(format!("Invalid {} {}", key, value)),
+                },
+            }
+        };
+
+        match key {
+            "width" => self.width = size()?,
+            "height" => self.height = size()?,
+            "fullscreen" => {
+                self.fullscreen = match value {
+                    "" => None,
+                    "false" | "no" => Some(false),
+                    "true" | "yes" => Some(true),
+                    _ => return Err(format!("Invalid fullscreen {}, use true or false", value)),
+                }
+            }
+            "server" => self.server = text(),
+            "port" => {
+                self.port = match value {
+                    "" => None,
+                    _ => Some(value.parse().map_err(|_| format!("Invalid port {}", value))?),
+                }
+            }
+            "world" => self.world = text(),
+            "realm" => self.realm = text(),
+            _ => return Err(format!("Unknown game option {}", key)),
+        }
+
+        Ok(())
+    }
+
+    // One-off options on top of these. Going somewhere else
+    // replaces where the instance goes by default, a port alone
+    // keeps the default server.
+    pub fn with_overrides(&self, overrides: &GameOptions) -> GameOptions {
+        let mut retval = self.clone();
+
+        retval.width = overrides.width.or(self.width);
+        retval.height = overrides.height.or(self.height);
+        retval.fullscreen = overrides.fullscreen.or(self.fullscreen);
+
+        if overrides.server.is_some() || overrides.world.is_some() || overrides.realm.is_some() {
+            retval.server = overrides.server.clone();
+            retval.port = overrides.port;
+            retval.world = overrides.world.clone();
+            retval.realm = overrides.realm.clone();
+        } else if overrides.port.is_some() {
+            retval.port = overrides.port;
+        }
+
+        retval
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let targets = [&self.server, &self.world, &self.realm].iter().filter(|t| t.is_some()).count();
+        if targets > 1 {
+            return Err("Only one of server, world and realm can be set".to_string());
+        }
+
+        if self.port.is_some() && self.server.is_none() {
+            return Err("A port needs a server".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InstanceSettings {
+    // sizes as the jvm takes them, e.g. 512M or 4G
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_heap: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_heap: Option<String>,
+    pub gc: GcPreset,
+    // -D system properties
+    pub properties: BTreeMap<String, String>,
+    pub jvm_args: Vec<String>,
+    // command the game is started through, e.g. gamemoderun
+    pub wrapper: Vec<String>,
+    // environment variables of the game
+    pub env: BTreeMap<String, String>,
+    // shell commands run before the launch and after the game exits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_launch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_exit: Option<String>,
+    pub game: GameOptions,
+}
+
+impl InstanceSettings {
+    pub fn jvm_arguments(&self) -> Vec<String> {
+        let mut retvec = Vec::new();
+
+        if let Some(min_heap) = &self.min_heap {
+            retvec.push(format!("-Xms{}", min_heap));
+        }
+        if let Some(max_heap) = &self.max_heap {
+            retvec.push(format!("-Xmx{}", max_heap));
+        }
+
+        retvec.extend(self.gc.arguments().iter().map(|a| a.to_string()));
+
+        for (key, value) in &self.properties {
+            retvec.push(format!("-D{}={}", key, value));
+        }
+
+        retvec.extend(self.jvm_args.iter().cloned());
+        retvec
+    }
+
+    pub fn get(&self, key: &str) -> Result<String, String> {
+        if let Some(name) = key.strip_prefix("property.") {
+            return Ok(self.properties.get(name).cloned().unwrap_or_default());
+        }
+        if let Some(name) = key.strip_prefix("env.") {
+            return Ok(self.env.get(name).cloned().unwrap_or_default());
+        }
+        if let Some(value) = self.game.get(key) {
+            return Ok(value);
+        }
+
+        match key {
+            "min_heap" => Ok(self.min_heap.clone().unwrap_or_default()),
+            "max_heap" => Ok(self.max_heap.clone().unwrap_or_default()),
+            "gc" => Ok(self.gc.as_str().to_string()),
+            "jvm_args" => Ok(shell_words::join(&self.jvm_args)),
+            "wrapper" => Ok(shell_words::join(&self.wrapper)),
+            "pre_launch" => Ok(self.pre_launch.clone().unwrap_or_default()),
+            "post_exit" => Ok(self.post_exit.clone().unwrap_or_default()),
+            _ => Err(format!("Unknown setting {}, settings are {}", key, KEYS.join(", "))),
+        }
+    }
+
+    // Change a setting, an empty value resets it
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+
+        if GAME_KEYS.contains(&key) {
+            return self.game.set(key, value);
+        }
+
+        if let Some(name) = key.strip_prefix("property.") {
+            if name.is_empty() || name.contains(|c: char| c == '=' || c.is_whitespace()) {
+                return Err(format!("Invalid property name {}", name));
+            }
+
+            if value.is_empty() {
+                self.properties.remove(name);
+            } else {
+                self.properties.insert(name.to_string(), value.to_string());
+            }
+            return Ok(());
+        }
+
+        if let Some(name) = key.strip_prefix("env.") {
+            if !valid_env_name(name) {
+                return Err(format!("Invalid environment variable name {}", name));
+            }
+
+            if value.is_empty() {
+                self.env.remove(name);
+            } else {
+                self.env.insert(name.to_string(), value.to_string());
+            }
+            return Ok(());
+        }
+
+        match key {
+            "min_heap" => self.min_heap = heap_setting(value)?,
+            "max_heap" => self.max_heap = heap_setting(value)?,
+            "gc" => self.gc = GcPreset::parse(value).ok_or(format!("Unknown gc preset {}, presets are default, g1 and zgc", value))?,
+            "jvm_args" => {
+                let args = shell_words::split(value).map_err(|e| e.to_string())?;
+                if let Some(arg) = args.iter().find(|a| !a.starts_with('-')) {
+                    return Err(format!("{} isn't a jvm option", arg));
+                }
+                self.jvm_args = args;
+            }
+            "wrapper" => self.wrapper = shell_words::split(value).map_err(|e| e.to_string())?,
+            "pre_launch" => self.pre_launch = Some(value.to_string()).filter(|v| !v.is_empty()),
+            "post_exit" => self.post_exit = Some(value.to_string()).filter(|v| !v.is_empty()),
+            _ => return Err(format!("Unknown setting {}, settings are {}", key, KEYS.join(", "))),
+        }
+
+        Ok(())
+    }
+
+    // Check the settings together, `java_major` is the version of
+    // the java the instance runs on (None if it couldn't be run)
+    pub fn validate(&self, java_major: Option<u32>) -> Result<(), String> {
+        self.game.validate()?;
+
+        let min = self.min_heap.as_deref().and_then(parse_size);
+        let max = self.max_heap.as_deref().and_then(parse_size);
+
+        if let (Some(min), Some(max)) = (min, max) {
+            if min > max {
+                return Err("The minimum heap is larger than the maximum heap".to_string());
+            }
+        }
+
+        if let Some(max) = max {
+            match physical_memory() {
+                Some(memory) if max > memory => {
+                    return Err(format!(
+                        "The maximum heap is more than the {}M of memory this machine has",
+                        memory / (1024 * 1024)
+                    ));
+                }
+                Some(_) => (),
+                None => println!(
+                    "{} couldn't find out how much memory this machine has, the maximum heap wasn't checked",
+                    Yellow.paint("Warning:")
+                ),
+            }
+        }
+
+        if self.gc.min_java() > 0 {
+            match java_major {
+                Some(major) if major >= self.gc.min_java() => (),
+                Some(major) => {
+                    return Err(format!(
+                        "The {} preset needs java {}, this instance runs on java {}",
+                        self.gc.as_str(), self.gc.min_java(), major
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        "Couldn't tell which java this instance runs on, the {} preset needs java {}",
+                        self.gc.as_str(), self.gc.min_java()
+                    ))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn display(&self) {
+        for key in &["min_heap", "max_heap", "gc", "jvm_args", "wrapper", "pre_launch", "post_exit"] {
+            println!("{} = {}", key, self.get(key).unwrap_or_default());
+        }
+        for key in GAME_KEYS {
+            println!("{} = {}", key, self.get(key).unwrap_or_default());
+        }
+        for (name, value) in &self.properties {
+            println!("property.{} = {}", name, value);
+        }
+        for (name, value) in &self.env {
+            println!("env.{} = {}", name, value);
+        }
+    }
+}
+
+pub fn valid_env_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(|c: char| c == '=' || c == '\0' || c.is_whitespace())
+}
+
+// normalized heap size, None to reset it
+fn heap_setting(value: &str) -> Result<Option<String>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    match parse_size(value) {
+        Some(size) if size > 0 => Ok(Some(value.to_uppercase())),
+        _ => Err(format!("Invalid size {}, use e.g. 512M or 4G", value)),
+    }
+}
+
+// jvm size (1024, 512k, 512M, 4G) in bytes
+pub fn parse_size(size: &str) -> Option<u64> {
+    let re = Regex::new(r"^(\d+)([kKmMgG]?)$").unwrap();
+    let captures = re.captures(size.trim())?;
+
+    let number: u64 = captures[1].parse().ok()?;
+    let unit: u64 = match &captures[2] {
+        "k" | "K" => 1024,
+        "m" | "M" => 1024 * 1024,
+        "g" | "G" => 1024 * 1024 * 1024,
+        _ => 1,
+    };
+
+    number.checked_mul(unit)
+}
+
+// Total memory in bytes
+pub fn physical_memory() -> Option<u64> {
+    if cfg!(windows) {
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-Command", "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory"])
+            .output()
+            .ok()?;
+        return String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+    }
+
+    if cfg!(target_os = "macos") {
+        let output = Command::new("sysctl").args(["-n", "hw.memsize"]).output().ok()?;
+        return String::from_utf8_lossy(&output.stdout).trim().parse().ok();
+    }
+
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+
+    // MemTotal:       16318048 kB
+    let line = meminfo.lines().find(|l| l.starts_with("MemTotal:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1024"), Some(1024));
+        assert_eq!(parse_size("512k"), Some(512 * 1024));
+        assert_eq!(parse_size("512M"), Some(512 * 1024 * 1024));
+        assert_eq!(parse_size("4g"), Some(4 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("4GB"), None);
+        assert_eq!(parse_size("-1G"), None);
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn set_and_arguments() {
+        let mut settings = InstanceSettings::default();
+        assert!(settings.jvm_arguments().is_empty());
+
+        settings.set("min_heap", "512m").unwrap();
+        settings.set("max_heap", "1G").unwrap();
+        settings.set("gc", "zgc").unwrap();
+        settings.set("property.fml.earlyprogresswindow", "false").unwrap();
+        settings.set("jvm_args", "-XX:+AlwaysPreTouch '-Dname=with space'").unwrap();
+
+        assert_eq!(settings.jvm_arguments(), vec![
+            "-Xms512M",
+            "-Xmx1G",
+            "-XX:+UseZGC",
+            "-Dfml.earlyprogresswindow=false",
+            "-XX:+AlwaysPreTouch",
+            "-Dname=with space",
+        ]);
+        assert_eq!(settings.get("jvm_args").unwrap(), "-XX:+AlwaysPreTouch '-Dname=with space'");
+
+        // empty values reset
+        settings.set("min_heap", "").unwrap();
+        settings.set("property.fml.earlyprogresswindow", "").unwrap();
+        assert_eq!(settings.get("min_heap").unwrap(), "");
+        assert!(settings.properties.is_empty());
+
+        assert!(settings.set("max_heap", "lots").is_err());
+        assert!(settings.set("gc", "cms").is_err());
+        assert!(settings.set("jvm_args", "nogui").is_err());
+        assert!(settings.set("property.a=b", "c").is_err());
+        assert!(settings.set("heap", "1G").is_err());
+
+        settings.set("wrapper", "nice -n 5").unwrap();
+        settings.set("env.__GL_THREADED_OPTIMIZATIONS", "1").unwrap();
+        assert_eq!(settings.wrapper, vec!["nice", "-n", "5"]);
+        assert_eq!(settings.get("env.__GL_THREADED_OPTIMIZATIONS").unwrap(), "1");
+        assert!(settings.set("env.A B", "1").is_err());
+
+        // neither shows up in the jvm arguments
+        assert!(!settings.jvm_arguments().iter().any(|a| a == "nice" || a.contains("__GL")));
+    }
+
+    #[test]
+    fn game_options() {
+        let mut settings = InstanceSettings::default();
+        settings.set("width", "1280").unwrap();
+        settings.set("server", "mc.example.com").unwrap();
+        settings.set("port", "25566").unwrap();
+        assert!(settings.validate(Some(17)).is_ok());
+        assert_eq!(settings.get("port").unwrap(), "25566");
+
+        assert!(settings.set("width", "0").is_err());
+        assert!(settings.set("port", "70000").is_err());
+        assert!(settings.set("fullscreen", "maybe").is_err());
+
+        settings.set("world", "New World").unwrap();
+        assert!(settings.validate(Some(17)).is_err());
+        settings.set("world", "").unwrap();
+
+        // going somewhere else for one launch drops the default server
+        let overrides = GameOptions {
+            world: Some("New World".to_string()),
+            height: Some(720),
+            ..GameOptions::default()
+        };
+        let options = settings.game.with_overrides(&overrides);
+        assert_eq!(options.width, Some(1280));
+        assert_eq!(options.height, Some(720));
+        assert_eq!(options.server, None);
+        assert_eq!(options.port, None);
+        assert!(options.validate().is_ok());
+
+        // a port alone keeps the server, windowed beats the default
+        settings.set("fullscreen", "true").unwrap();
+        let overrides = GameOptions {
+            port: Some(25567),
+            fullscreen: Some(false),
+            ..GameOptions::default()
+        };
+        let options = settings.game.with_overrides(&overrides);
+        assert_eq!(options.server, Some("mc.example.com".to_string()));
+        assert_eq!(options.port, Some(25567));
+        assert_eq!(options.fullscreen, Some(false));
+    }
+
+    #[test]
+    fn validation() {
+        let mut settings = InstanceSettings::default();
+        settings.set("min_heap", "2G").unwrap();
+        settings.set("max_heap", "1G").unwrap();
+        assert!(settings.validate(Some(8)).is_err());
+
+        settings.set("min_heap", "").unwrap();
+        assert!(settings.validate(Some(8)).is_ok());
+
+        settings.set("gc", "zgc").unwrap();
+        assert!(settings.validate(Some(8)).is_err());
+        assert!(settings.validate(Some(17)).is_ok());
+        assert!(settings.validate(None).is_err());
+
+        if physical_memory().is_some() {
+            settings.set("max_heap", "100000G").unwrap();
+            assert!(settings.validate(Some(17)).is_err());
+        }
+    }
+}
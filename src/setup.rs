@@ -14,29 +14,31 @@ You should have received a copy of the GNU General Public License along with thi
 
 
 use crate::instance::Instance;
-use crate::util;
+use crate::maven::{ComparableVersion, MavenCoordinate};
+use crate::profile::VersionProfile;
+use crate::rules::{self, RuleContext};
 
 use crate::{
     cf::CFFile,
-    downloader::Downloader,
+    downloader::{self, Downloader},
 };
 
-//use serde_json::*;
+use serde_json::Value;
 
 use log::{info, warn};
 
 use std::{
     collections::HashMap,
     fs,
-    fs::{File, OpenOptions},
-    io::BufReader,
-    path::PathBuf,
+    fs::File,
+    path::{Path, PathBuf},
 };
 
 use zip::ZipArchive;
 
 
-// needed for serde json serialization
+// repository for libraries that don't name one
+const MOJANG_LIBRARIES: &str = "https://libraries.minecraft.net/";
 
 pub async fn get_modslist(chosen_proj: CFFile, instance: Instance) {
     let download_url = chosen_proj.get_download_url();
@@ -70,164 +72,302 @@ pub async fn get_modslist(chosen_proj: CFFile, instance: Instance) {
     fs::remove_file(download_path).expect("Error deleting stage zip file");
 }
 
-pub fn get_cp_from_version(
-    libpath: PathBuf,
-    version_paths: Vec<PathBuf>,
-) -> Vec<(String, PathBuf)> {
-    let mut retvec = Vec::new();
+// Classpath of a version relative to the instance directory.
+// Version jars go last, after all libraries.
+pub fn get_classpath(
+    instance_path: PathBuf,
+    profile: &VersionProfile,
+    rules: &RuleContext,
+) -> Vec<PathBuf> {
+    let mut retvec: Vec<PathBuf> = Vec::new();
+
+    for lib in profile.libraries() {
+        if !library_applies(&lib, rules) {
+            continue;
+        }
 
-    for version_fpath in version_paths {
+        let path = match library_artifact(&lib) {
+            Some((path, _)) => PathBuf::from("libraries").join(path),
+            None => {
+                // natives only libraries are handled by get_binaries
+                if lib["natives"].is_null() && lib["downloads"]["classifiers"].is_null() {
+                    println!("Couldn't get library path for {}, skipping", lib["name"]);
+                }
+                continue;
+            }
+        };
 
-        // add version jar to path
+        if !retvec.contains(&path) {
+            retvec.push(path);
+        }
+    }
+
+    // add version jars to path
+    for id in &profile.chain {
+        let mut version_jarpath = PathBuf::from("versions");
+        version_jarpath.push(id);
+        version_jarpath.push(format!("{}.jar", id));
+
+        if instance_path.join(&version_jarpath).exists() {
+            retvec.push(version_jarpath);
+        }
+    }
 
-        let mut version_jarpath = version_fpath.clone();
-        version_jarpath.set_extension("jar");
+    retvec
+}
 
-        if version_jarpath.exists() {
-            let mut relative_version_jarpath = PathBuf::from("./versions");
-            relative_version_jarpath.push(version_jarpath.file_name().unwrap());
+// Path (relative to the libraries folder) and download url of a
+// library's main jar. Libraries without `downloads` (older forge
+// jsons) are resolved from their name and `url` repository.
+pub fn library_artifact(lib: &Value) -> Option<(PathBuf, Option<String>)> {
+    let artifact = &lib["downloads"]["artifact"];
 
-            retvec.push(("version_jar".to_string(), relative_version_jarpath));
+    if !lib["downloads"].is_null() {
+        // only natives
+        if artifact.is_null() {
+            return None;
         }
 
-        let file = File::open(version_fpath).unwrap();
-        let reader = BufReader::new(file);
+        let path = match artifact["path"].as_str() {
+            Some(path) => path.to_string(),
+            None => MavenCoordinate::parse(lib["name"].as_str()?)?.path(),
+        };
 
-        // Read the JSON contents of the file as an instance of `User`.
-        let u: serde_json::Value = serde_json::from_reader(reader).unwrap();
+        let url = artifact["url"]
+            .as_str()
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_string());
 
-        let libraries = u["libraries"].as_array().unwrap();
+        return Some((PathBuf::from(path), url));
+    }
 
-        for lib in libraries {
-            let artifact: Vec<&str> = lib["name"].as_str().unwrap().split(":").collect();
+    if !lib["natives"].is_null() {
+        return None;
+    }
 
-            let name = artifact[1];
-            let version = artifact[2];
+    let coordinate = MavenCoordinate::parse(lib["name"].as_str()?)?;
+    let repository = lib["url"].as_str().unwrap_or(MOJANG_LIBRARIES);
 
-            let full_name = format!("{}:{}:{}", artifact[0], artifact[1], artifact[2]);
+    Some((PathBuf::from(coordinate.path()), Some(coordinate.url(repository))))
+}
 
-            let mut path = libpath.clone();
-            path.push(match lib["downloads"]["artifact"]["path"].as_str() {
-                Some(val) => val,
-                None => {
-                    println!("Couldn't get library path for {}, skipping", lib["downloads"]["artifact"]["name"]);
-                    ""
-                }
-            });
-
-            // this excludes forge or any other invalid lib for the check
-            // since they don't have urls
-            if lib["downloads"]["artifact"]["url"].as_str().is_none() {
-                retvec.push((full_name, path));
-            } else {
-
-                let mut found_version = Some(String::new());
-                let found_index = retvec.iter().position(|v| {
-                    let a = &v.0;
-                    let n: Vec<&str> = a.split(":").collect();
-
-                    if n.len() >= 3 {
-                        found_version = Some(n[2].to_string());
-                        name == n[1]
-                    }else{
-                        false
-                    }
+// Natives jar of an old style library (one with a `natives` map),
+// as its path in the libraries folder and download url
+pub fn library_natives(lib: &Value, rules: &RuleContext) -> Option<(PathBuf, Option<String>)> {
+    let classifier = lib["natives"][rules.os_name.as_str()]
+        .as_str()?
+        .replace("${arch}", rules.arch_bits());
+
+    if !lib["downloads"].is_null() {
+        let download = &lib["downloads"]["classifiers"][classifier.as_str()];
+        let path = download["path"].as_str()?;
+        let url = download["url"]
+            .as_str()
+            .filter(|url| !url.is_empty())
+            .map(|url| url.to_string());
+
+        return Some((PathBuf::from(path), url));
+    }
 
-                });
-
-                // make some checks for duplicate library
-                if found_index.is_some() {
-                    match found_version {
-                        Some(other_version) => {
-                            if util::geq_version(version, other_version.as_str()) {
-                                // remove old version and keep new one
-                                retvec.remove(found_index.unwrap());
-                                retvec.push((full_name, path));
-                            }
-                        },
-                        None => ()
-                    }
-                    
-                    // if prev entry has greater version,
-                    // then don't push anything
-                } else {
-                    // no duplicates found, may push
-                    retvec.push((full_name, path));
-                }
-            }
-        }
+    let coordinate = MavenCoordinate::parse(lib["name"].as_str()?)?.with_classifier(classifier.as_str());
+    let repository = lib["url"].as_str().unwrap_or(MOJANG_LIBRARIES);
+
+    Some((PathBuf::from(coordinate.path()), Some(coordinate.url(repository))))
+}
+
+// Since 1.19 natives are regular libraries with a
+// `natives-<os>[-<arch>]` classifier. Their rules only check the
+// os, so the arch has to be taken from the classifier.
+fn library_applies(lib: &Value, rules: &RuleContext) -> bool {
+    if !rules.allows(&lib["rules"]) {
+        return false;
     }
 
-    retvec
+    let classifier = match lib["name"].as_str().and_then(MavenCoordinate::parse) {
+        Some(coordinate) => coordinate.classifier.unwrap_or_default(),
+        None => return true,
+    };
+
+    if !classifier.starts_with("natives-") {
+        return true;
+    }
+
+    // natives-linux is the x86_64 build
+    let arch = classifier.splitn(3, '-').nth(2).unwrap_or("x86_64");
+    rules::arch_matches(arch, rules.os_arch.as_str())
 }
 
 pub async fn get_library_downloads(
     libpath: PathBuf,
-    manifest: PathBuf,
+    profile: &VersionProfile,
+    rules: &RuleContext,
 ) -> Option<HashMap<PathBuf, String>> {
 
     let mut lib_downloads: HashMap<PathBuf, String> = HashMap::new();
 
-    let file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(manifest)
-        .unwrap();
-
-    let json: serde_json::Value = serde_json::from_reader(file).unwrap();
-    let libraries = json["libraries"]
-        .as_array()
-        .expect("Error getting libraries.");
-
-    for (_i, lib) in libraries.iter().enumerate() {
-        let artifact_path = match lib["downloads"]["artifact"]["path"].as_str() {
-            Some(val) =>  {
-                val
-            },
+    for lib in profile.libraries() {
+        if !library_applies(&lib, rules) {
+            continue;
+        }
+
+        let (artifact_path, download_url) = match library_artifact(&lib) {
+            Some(val) => val,
             None => {
-                // skipping on empty path
-                eprintln!("EMPTY PATH Skipping {}", lib["name"].as_str()?);
-                break;
+                // natives only libraries are handled by get_binaries
+                if lib["natives"].is_null() && lib["downloads"]["classifiers"].is_null() {
+                    eprintln!("EMPTY PATH Skipping {}", lib["name"].as_str()?);
+                }
+                continue;
             }
         };
 
         let mut path = libpath.clone();
         path.push(artifact_path);
 
-        let download_url = match lib["downloads"]["artifact"]["url"].as_str() {
-            Some(val) => val,
-            None => {
-                // skipping on empty url
-                eprintln!("EMPTY URL Skipping {}", lib["name"].as_str()?);
+        // mod loader installers put their own libraries in place,
+        // those usually aren't hosted anywhere
+        if path.exists() {
+            continue;
+        }
 
-                break;
+        match download_url {
+            Some(url) => {
+                lib_downloads.insert(path, url);
             }
-        };
-
-        // only download if url is valid and
-        // the downloads hashmap doesn't contain
-        // the key ( avoid dupes )
-        if !download_url.is_empty() {
-            lib_downloads.insert(path, download_url.to_string());
-        }else{
-            eprintln!("Download url is empty {}", lib["name"].as_str()?);
+            None => eprintln!("Download url is empty {}", lib["name"].as_str()?),
         }
     }
 
     Some(lib_downloads)
 }
 
+// client jar goes to versions/<id>/<id>.jar like the vanilla launcher does
+pub fn get_client_download(
+    instance_path: PathBuf,
+    profile: &VersionProfile,
+) -> Option<(PathBuf, String)> {
+    let url = profile.json()["downloads"]["client"]["url"].as_str()?;
+
+    let jar_id = profile.jar_id();
+    let mut jar_path = instance_path;
+    jar_path.push("versions");
+    jar_path.push(jar_id.as_str());
+    jar_path.push(format!("{}.jar", jar_id));
+
+    Some((jar_path, url.to_string()))
+}
+
+// Download everything a version needs to launch:
+// libraries, natives, the client jar and assets
+pub async fn install_version(
+    instance_path: PathBuf,
+    profile: &VersionProfile,
+    rules: &RuleContext,
+) {
+    get_binaries(profile, instance_path.clone(), rules).await;
+
+    let mut libpath = instance_path.clone();
+    libpath.push("libraries");
+
+    // get a list of all downloads
+    let mut downloads = get_library_downloads(libpath, profile, rules).await.unwrap();
+
+    if let Some((jar_path, url)) = get_client_download(instance_path.clone(), profile) {
+        // mod loader installers usually fetched it already
+        if !jar_path.exists() {
+            downloads.insert(jar_path, url);
+        }
+    }
+
+    if let Some((config_path, url, sha1)) = get_logging_download(&instance_path, profile) {
+        if !downloader::verify_sha1(&config_path, sha1.as_str()) {
+            downloads.insert(config_path, url);
+        }
+    }
+
+    if let Some(assets) = get_asset_downloads(instance_path.clone(), profile).await {
+        downloads.extend(assets);
+    }
+
+    Downloader::new(downloads)
+        .process()
+        .await
+        .expect("Unable to finish download");
+
+    copy_legacy_assets(&instance_path, profile).expect("Unable to copy legacy assets");
+}
+
+// client log4j config as where it goes, its url and sha1
+pub fn get_logging_download(instance_path: &Path, profile: &VersionProfile) -> Option<(PathBuf, String, String)> {
+    let file = &profile.json()["logging"]["client"]["file"];
+    let id = file["id"].as_str()?;
+
+    let mut config_path = instance_path.to_path_buf();
+    config_path.push("assets");
+    config_path.push("log_configs");
+    config_path.push(id);
+
+    Some((
+        config_path,
+        file["url"].as_str()?.to_string(),
+        file["sha1"].as_str().unwrap_or("").to_string(),
+    ))
+}
+
+// log4j-core before 2.17.1 is open to log4shell and friends
+fn log4j_vulnerable(profile: &VersionProfile) -> bool {
+    profile.libraries().iter().any(|lib| {
+        match lib["name"].as_str().and_then(MavenCoordinate::parse) {
+            Some(c) => {
+                c.group == "org.apache.logging.log4j"
+                    && c.artifact == "log4j-core"
+                    && ComparableVersion::parse(c.version.as_str()) < ComparableVersion::parse("2.17.1")
+            }
+            None => false,
+        }
+    })
+}
+
+// Jvm arguments for logging: the version's log4j config, and
+// disabled message lookups for vulnerable log4j versions whose
+// config isn't one of mojang's patched ones
+pub fn logging_arguments(instance_path: &Path, profile: &VersionProfile) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut patched = false;
+
+    let argument = profile.json()["logging"]["client"]["argument"].as_str();
+
+    if let (Some(argument), Some((config_path, _, sha1))) = (argument, get_logging_download(instance_path, profile)) {
+        if downloader::verify_sha1(&config_path, sha1.as_str()) {
+            let relative = config_path.strip_prefix(instance_path).unwrap_or(&config_path);
+            args.push(argument.replace("${path}", format!("./{}", relative.display()).as_str()));
+
+            // patched configs print messages with %msg{nolookups}
+            patched = fs::read_to_string(&config_path)
+                .map(|config| config.contains("nolookups"))
+                .unwrap_or(false);
+        } else {
+            warn!("Log config {} doesn't match its hash, not using it", config_path.display());
+        }
+    }
+
+    if !patched && log4j_vulnerable(profile) {
+        args.push("-Dlog4j2.formatMsgNoLookups=true".to_string());
+    }
+
+    args
+}
+
 pub async fn get_asset_downloads(
     game_path: PathBuf,
-    version_path: PathBuf,
+    profile: &VersionProfile,
 ) -> Option<HashMap<PathBuf, String>> {
 
     let mut asset_downloads: HashMap<PathBuf, String> = HashMap::new();
 
     let request_client = reqwest::Client::new();
 
-    let version_file = File::open(version_path).unwrap();
-    let version: serde_json::Value = serde_json::from_reader(version_file).unwrap();
+    let version = profile.json();
 
     let url = version["assetIndex"]["url"].as_str()?;
 
@@ -249,7 +389,7 @@ pub async fn get_asset_downloads(
 
     let asset_objects = assets_json["objects"].as_object().unwrap();
 
-    for (_i, object) in asset_objects.iter().enumerate() {
+    for object in asset_objects.iter() {
         let hash = object.1["hash"].as_str().unwrap();
         let first_two = &hash[0..2];
 
@@ -270,6 +410,89 @@ pub async fn get_asset_downloads(
     Some(asset_downloads)
 }
 
+// parsed asset index of a version, once it's downloaded
+fn read_asset_index(instance_path: &Path, profile: &VersionProfile) -> Option<(String, Value)> {
+    let id = profile.json()["assetIndex"]["id"].as_str()?;
+
+    let mut index_path = instance_path.to_path_buf();
+    index_path.push("assets");
+    index_path.push("indexes");
+    index_path.push(format!("{}.json", id));
+
+    let data = fs::read_to_string(index_path).ok()?;
+    Some((id.to_string(), serde_json::from_str(data.as_str()).ok()?))
+}
+
+// Versions before 1.7.2 look assets up by name instead of by
+// hash. `virtual` indexes (legacy) expect them in
+// assets/virtual/<index>, `map_to_resources` ones (pre-1.6) in
+// the resources folder of the game directory.
+fn legacy_assets_dir(index_id: &str, index: &Value) -> Option<String> {
+    if index["map_to_resources"].as_bool().unwrap_or(false) {
+        Some("resources".to_string())
+    } else if index["virtual"].as_bool().unwrap_or(false) {
+        Some(format!("assets/virtual/{}", index_id))
+    } else {
+        None
+    }
+}
+
+// value of `${game_assets}`, relative to the instance directory
+pub fn game_assets(instance_path: &Path, profile: &VersionProfile) -> String {
+    let dir = read_asset_index(instance_path, profile)
+        .and_then(|(id, index)| legacy_assets_dir(id.as_str(), &index));
+
+    match dir {
+        Some(dir) => format!("./{}", dir),
+        None => "./assets".to_string(),
+    }
+}
+
+// copy downloaded objects to their names for indexes that need it
+pub fn copy_legacy_assets(instance_path: &Path, profile: &VersionProfile) -> std::io::Result<()> {
+    let (id, index) = match read_asset_index(instance_path, profile) {
+        Some(val) => val,
+        None => return Ok(()),
+    };
+
+    let dir = match legacy_assets_dir(id.as_str(), &index) {
+        Some(dir) => instance_path.join(dir),
+        None => return Ok(()),
+    };
+
+    let objects = match index["objects"].as_object() {
+        Some(objects) => objects,
+        None => return Ok(()),
+    };
+
+    info!("Copying {} assets to {}", objects.len(), dir.display());
+
+    for (name, object) in objects {
+        let hash = match object["hash"].as_str() {
+            Some(hash) => hash,
+            None => continue,
+        };
+
+        let mut object_path = instance_path.to_path_buf();
+        object_path.push("assets");
+        object_path.push("objects");
+        object_path.push(&hash[0..2]);
+        object_path.push(hash);
+
+        let target = dir.join(name);
+        if target.exists() {
+            continue;
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(object_path, target)?;
+    }
+
+    Ok(())
+}
+
 pub async fn get_mod_downloads(
     mc_version: String,
     mods_path: PathBuf,
@@ -304,8 +527,8 @@ pub async fn get_mod_downloads(
         let for_versions = mod_json["versions"].as_array();
 
         // if versions key exists in the mod manifest json, use that
-        if for_versions.is_some() {
-            for version in for_versions.unwrap() {
+        if let Some(for_versions) = for_versions {
+            for version in for_versions {
                 let v = version.as_str().unwrap();
                 if v == mc_version {
                     // search the fileID here
@@ -344,7 +567,7 @@ pub async fn get_mod_downloads(
 
             let mut mod_found = false; 
 
-            for (_i, modfile) in modfiles.iter().enumerate() {
+            for modfile in modfiles.iter() {
                 // found right mod file now download it
                 if modfile["id"].as_u64().unwrap() == file_id {
                     let cf_file = CFFile {
@@ -368,12 +591,12 @@ pub async fn get_mod_downloads(
             // download based on mc version
 
             if !mod_found {
-                for (_i, modfile) in modfiles.iter().enumerate() {
+                for modfile in modfiles.iter() {
                     let modfile_mc_versions = modfile["versions"].as_array().unwrap();
                     for version in modfile_mc_versions {
                         let version_str = version.as_str().unwrap();
 
-                        if version_str.to_string() == mc_version {
+                        if version_str == mc_version {
 
                             let cf_file = CFFile {
                                 id: modfile["id"].as_u64().unwrap(),
@@ -402,55 +625,47 @@ pub async fn get_mod_downloads(
     Some(downloads_map)
 }
 
-pub async fn get_binaries(version_path: PathBuf, instance_path: PathBuf) {
-    let manifest_file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(version_path)
-        .expect("Couldn't open version path");
-
-    let json: serde_json::Value =
-        serde_json::from_reader(manifest_file).expect("Could not parse version file");
+// Download natives of old style libraries and extract them to bin/
+pub async fn get_binaries(profile: &VersionProfile, instance_path: PathBuf, rules: &RuleContext) {
+    let mut libpath = instance_path.clone();
+    libpath.push("libraries");
 
-    let libs = json["libraries"].as_array().unwrap();
-    let os = std::env::consts::OS;
-
-    let mut jarpaths: Vec<PathBuf> = Vec::new();
+    let mut binpath = instance_path;
+    binpath.push("bin");
 
+    let mut natives: Vec<(PathBuf, Vec<String>)> = Vec::new();
     let mut download_map : HashMap<PathBuf, String> = HashMap::new();
-    // Download jars
-    for lib in libs {
-        if !lib["downloads"]["classifiers"].is_null() {
-            let natives_id: &str = match os {
-                "windows" => "natives-windows",
-                "macos" => "natives-macos",
-                "linux" => "natives-linux",
-                _ => "",
-            };
-
-            if natives_id != "" {
-                let url = match lib["downloads"]["classifiers"][natives_id]["url"].as_str() {
-                    Some(s) => s,
-                    None => break,
-                };
 
-                let path = match lib["downloads"]["classifiers"][natives_id]["path"].as_str() {
-                    Some(s) => s,
-                    None => break,
-                };
-
-
-                let mut fullpath = instance_path.clone();
-                fullpath.push("libraries");
-                fullpath.push(path);
+    for lib in profile.libraries() {
+        if !rules.allows(&lib["rules"]) {
+            continue;
+        }
 
-                download_map.insert(fullpath, url.to_string());
-                jarpaths.push(PathBuf::from(path));
+        // libraries without natives for this os are skipped
+        let (path, url) = match library_natives(&lib, rules) {
+            Some(val) => val,
+            None => continue,
+        };
 
-            } else {
-                panic!("Couldn't detect OS");
+        let fullpath = libpath.join(path);
+        if !fullpath.exists() {
+            match url {
+                Some(url) => {
+                    download_map.insert(fullpath.clone(), url);
+                }
+                None => {
+                    eprintln!("Download url is empty {}", lib["name"]);
+                    continue;
+                }
             }
         }
+
+        let exclude: Vec<String> = match lib["extract"]["exclude"].as_array() {
+            Some(exclude) => exclude.iter().filter_map(|e| e.as_str()).map(|e| e.to_string()).collect(),
+            None => Vec::new(),
+        };
+
+        natives.push((fullpath, exclude));
     }
 
     // Download binaries
@@ -460,24 +675,215 @@ pub async fn get_binaries(version_path: PathBuf, instance_path: PathBuf) {
         .expect("Unable to download file");
 
     // Extract jars
-    for jarpath in jarpaths {
-        let mut fullpath = instance_path.clone();
-        fullpath.push("libraries/");
-        fs::create_dir_all(fullpath.clone()).expect("Couldn't create binary directory");
-        fullpath.push(jarpath);
-
-        let jarfile = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(fullpath)
-            .unwrap();
+    fs::create_dir_all(binpath.clone()).expect("Couldn't create binary directory");
+    for (jarpath, exclude) in natives {
+        extract_natives(&jarpath, &binpath, &exclude).expect("Couldn't extract binary.");
+    }
+}
+
+// Extract a natives jar, leaving out the jar metadata and every
+// entry that starts with one of the `exclude` prefixes
+fn extract_natives(jarpath: &Path, binpath: &Path, exclude: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut za = ZipArchive::new(File::open(jarpath)?)?;
+
+    for i in 0..za.len() {
+        let mut entry = za.by_index(i)?;
+        let name = entry.name().to_string();
+
+        if name.starts_with("META-INF/") || exclude.iter().any(|e| name.starts_with(e.as_str())) {
+            continue;
+        }
 
-        let mut za = ZipArchive::new(jarfile).unwrap();
-        let mut binpath = instance_path.clone();
-        binpath.push("bin/");
-        fs::create_dir_all(binpath.clone()).expect("Couldn't create binary directory");
-        za.extract(binpath).expect("Couldn't extract binary.");
+        let outpath = match entry.enclosed_name() {
+            Some(path) => binpath.join(path),
+            None => continue,
+        };
+
+        if entry.is_dir() {
+            fs::create_dir_all(&outpath)?;
+            continue;
+        }
+
+        if let Some(parent) = outpath.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut outfile = File::create(&outpath)?;
+        std::io::copy(&mut entry, &mut outfile)?;
     }
+
+    Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Version jsons in tests/fixtures/versions follow mojang's
+    // (and the forge installer's) format. Their library lists are
+    // trimmed to what matters for the classpath: rules, natives,
+    // duplicated entries and libraries without downloads. Every
+    // version has an empty client jar next to its json.
+    //
+    // tests/fixtures/classpaths has the full classpath expected for
+    // each of them, one path per line, named <version>-<os>.txt.
+    fn fixtures_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+    }
+
+    fn load(id: &str) -> VersionProfile {
+        VersionProfile::load(&fixtures_path().join("versions"), id).unwrap()
+    }
+
+    fn classpath(id: &str, rules: &RuleContext) -> Vec<String> {
+        get_classpath(fixtures_path(), &load(id), rules)
+            .iter()
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .collect()
+    }
+
+    fn expected(name: &str) -> Vec<String> {
+        let path = fixtures_path().join("classpaths").join(format!("{}.txt", name));
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    fn linux() -> RuleContext {
+        RuleContext::new("linux", "x86_64", "6.1.0")
+    }
+
+    fn has(classpath: &[String], path: &str) -> bool {
+        classpath.iter().any(|p| p == path)
+    }
+
+    fn assert_no_duplicates(classpath: &[String]) {
+        for (i, path) in classpath.iter().enumerate() {
+            assert!(!classpath[i + 1..].contains(path), "{} is on the classpath twice", path);
+        }
+    }
+
+    #[test]
+    fn classpath_1_7_10() {
+        let cp = classpath("1.7.10", &linux());
+        assert_eq!(cp, expected("1.7.10-linux"));
+
+        // the nightly is for osx only
+        assert!(!has(&cp, "libraries/org/lwjgl/lwjgl/lwjgl/2.9.1-nightly-20140716/lwjgl-2.9.1-nightly-20140716.jar"));
+        // natives are extracted, not put on the classpath
+        assert!(cp.iter().all(|p| !p.contains("natives")));
+        assert_no_duplicates(&cp);
+    }
+
+    #[test]
+    fn classpath_1_12_2() {
+        let cp = classpath("1.12.2", &linux());
+        assert_eq!(cp, expected("1.12.2-linux"));
+        assert!(cp.iter().all(|p| !p.contains("natives")));
+        assert_no_duplicates(&cp);
+
+        let osx = classpath("1.12.2", &RuleContext::new("osx", "x86_64", "10.15"));
+        assert_eq!(osx, expected("1.12.2-osx"));
+        assert!(!has(&osx, "libraries/org/lwjgl/lwjgl/lwjgl/2.9.4-nightly-20150209/lwjgl-2.9.4-nightly-20150209.jar"));
+    }
+
+    #[test]
+    fn classpath_1_12_2_forge() {
+        let cp = classpath("1.12.2-forge-14.23.5.2855", &linux());
+
+        // forge libraries come first, resolved from their maven
+        // coordinates, and forge uses the vanilla client jar
+        assert_eq!(cp, expected("1.12.2-forge-14.23.5.2855-linux"));
+        assert_no_duplicates(&cp);
+
+        // jopt-simple is declared by both, the same version is kept once
+        assert_eq!(cp.iter().filter(|p| p.contains("jopt-simple")).count(), 1);
+    }
+
+    #[test]
+    fn classpath_1_16_5() {
+        let cp = classpath("1.16.5", &linux());
+        assert_eq!(cp, expected("1.16.5-linux"));
+
+        assert!(!has(&cp, "libraries/org/lwjgl/lwjgl/3.2.1/lwjgl-3.2.1.jar"));
+        assert!(!has(&cp, "libraries/ca/weblite/java-objc-bridge/1.0.0/java-objc-bridge-1.0.0.jar"));
+        assert!(cp.iter().all(|p| !p.contains("natives")));
+        assert_no_duplicates(&cp);
+    }
+
+    #[test]
+    fn classpath_1_20_1() {
+        let cp = classpath("1.20.1", &linux());
+        assert_eq!(cp, expected("1.20.1-linux"));
+
+        // lwjgl 3.3 puts its natives on the classpath
+        assert!(cp.iter().all(|p| !p.contains("natives-macos") && !p.contains("natives-windows")));
+        assert_eq!(cp.iter().filter(|p| p.contains("natives-linux")).count(), 7);
+        assert_no_duplicates(&cp);
+    }
+
+    #[test]
+    fn classpath_1_20_1_natives_by_arch() {
+        let windows = classpath("1.20.1", &RuleContext::new("windows", "arm64", "10.0"));
+        assert_eq!(windows, expected("1.20.1-windows"));
+        assert!(!has(&windows, "libraries/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-windows.jar"));
+        assert!(!has(&windows, "libraries/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-windows-x86.jar"));
+
+        let osx = classpath("1.20.1", &RuleContext::new("osx", "x86_64", "13.0"));
+        assert_eq!(osx, expected("1.20.1-osx"));
+        assert!(!has(&osx, "libraries/org/lwjgl/lwjgl/3.3.1/lwjgl-3.3.1-natives-macos-arm64.jar"));
+    }
+
+    #[test]
+    fn natives_from_natives_map() {
+        let profile = load("1.12.2");
+        let text2speech = profile
+            .libraries()
+            .into_iter()
+            .find(|l| l["name"] == "com.mojang:text2speech:1.10.3")
+            .unwrap();
+
+        let (path, url) = library_natives(&text2speech, &linux()).unwrap();
+        assert_eq!(path, PathBuf::from("com/mojang/text2speech/1.10.3/text2speech-1.10.3-natives-linux.jar"));
+        assert!(url.unwrap().ends_with("text2speech-1.10.3-natives-linux.jar"));
+
+        // no natives for osx
+        assert!(library_natives(&text2speech, &RuleContext::new("osx", "x86_64", "10.15")).is_none());
+    }
+
+    #[test]
+    fn natives_arch_placeholder() {
+        let profile = load("1.7.10");
+        let twitch = profile
+            .libraries()
+            .into_iter()
+            .find(|l| l["name"] == "tv.twitch:twitch-external-platform:4.5")
+            .unwrap();
+
+        let (path, _) = library_natives(&twitch, &RuleContext::new("windows", "x86", "6.1")).unwrap();
+        assert_eq!(path, PathBuf::from("tv/twitch/twitch-external-platform/4.5/twitch-external-platform-4.5-natives-windows-32.jar"));
+
+        let (path, _) = library_natives(&twitch, &RuleContext::new("windows", "x86_64", "10.0")).unwrap();
+        assert_eq!(path, PathBuf::from("tv/twitch/twitch-external-platform/4.5/twitch-external-platform-4.5-natives-windows-64.jar"));
+    }
 
+    #[test]
+    fn artifact_from_repository() {
+        let profile = load("1.12.2-forge-14.23.5.2855");
+        let libs = profile.libraries();
+
+        let forge = libs.iter().find(|l| l["name"] == "net.minecraftforge:forge:1.12.2-14.23.5.2855").unwrap();
+        let (_, url) = library_artifact(forge).unwrap();
+        assert_eq!(
+            url.unwrap(),
+            "https://maven.minecraftforge.net/net/minecraftforge/forge/1.12.2-14.23.5.2855/forge-1.12.2-14.23.5.2855.jar"
+        );
+
+        // no repository means mojang's
+        let launchwrapper = libs.iter().find(|l| l["name"] == "net.minecraft:launchwrapper:1.12").unwrap();
+        let (_, url) = library_artifact(launchwrapper).unwrap();
+        assert_eq!(url.unwrap(), "https://libraries.minecraft.net/net/minecraft/launchwrapper/1.12/launchwrapper-1.12.jar");
+    }
+}
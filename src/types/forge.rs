@@ -1,19 +1,29 @@
-use std::path::PathBuf;
+use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::fs::{self, OpenOptions};
 use log::info;
 use ansi_term::Color::{Red, Green, Yellow};
 use std::process::Command;
+use prettytable::Table;
 
 use crate::manager::InstanceManager;
+use crate::instance::Instance;
 use crate::downloader::Downloader;
 use crate::instance::InstanceType;
 use crate::cf::CFProject;
 use crate::setup;
 use crate::invoker::Invoker;
 use crate::util;
+use crate::config::Config;
+use crate::java;
+use crate::maven::ComparableVersion;
 use crate::auth::User;
+use crate::rules::RuleContext;
+use crate::arguments::Arguments;
+use crate::profile::{self, VersionProfile};
+use crate::versions::{self, VersionManifest};
 
-static FORGE_PRE13_ID_BLACKLIST : [&str;2] = ["forge-14.23.5.2838",  "forge-1.12.2-14.23.5.2847"]; 
+const FORGE_MAVEN: &str = "https://maven.minecraftforge.net/net/minecraftforge/forge";
 
 
 use std::collections::HashMap;
@@ -21,8 +31,8 @@ pub async fn download_installer(instance_path: PathBuf, mc_forge_version: String
     
     let mut forge_map = HashMap::new();
     let forge_url = format!(
-        "https://files.minecraftforge.net/maven/net/minecraftforge/forge/{}/forge-{}-installer.jar",
-        mc_forge_version, mc_forge_version
+        "{}/{}/forge-{}-installer.jar",
+        FORGE_MAVEN, mc_forge_version, mc_forge_version
     );
 
     let forge_fname = format!("forge-{}-installer.jar", mc_forge_version);
@@ -57,6 +67,7 @@ pub async fn download_headless_installer(instance_path: PathBuf) {
 pub fn run_forge_installation(
     instance_path: PathBuf, 
     installer_cp: String, 
+    java: &str,
     post_13 : bool
     ) {
 
@@ -69,7 +80,7 @@ pub fn run_forge_installation(
             ".",
         ];
         // invoke the headless installer
-        Command::new("java")
+        Command::new(java)
             .args(args)
             .current_dir(instance_path)
             .status()
@@ -84,7 +95,7 @@ pub fn run_forge_installation(
             installer_cp.as_str(),
         ];
 
-        Command::new("java")
+        Command::new(java)
             .args(args)
             .current_dir(instance_path)
             .status()
@@ -93,6 +104,98 @@ pub fn run_forge_installation(
 
 }
 
+// How to pick the forge version for a minecraft version
+#[derive(Clone, Debug, PartialEq)]
+pub enum ForgeSelector {
+    Recommended,
+    Latest,
+    Version(String),
+}
+
+impl ForgeSelector {
+    // empty input means recommended
+    pub fn parse(s: &str) -> ForgeSelector {
+        match s.trim() {
+            "" | "recommended" => ForgeSelector::Recommended,
+            "latest" => ForgeSelector::Latest,
+            version => ForgeSelector::Version(version.to_string()),
+        }
+    }
+}
+
+// All forge versions for a minecraft version from the forge
+// maven metadata, newest first. Versions are without the
+// minecraft version prefix, e.g. `36.2.39` for `1.16.5-36.2.39`.
+pub async fn get_forge_versions(mcv: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let url = format!("{}/maven-metadata.xml", FORGE_MAVEN);
+    let metadata = reqwest::get(url.as_str()).await?.text().await?;
+
+    let re = regex::Regex::new(r"<version>([^<]+)</version>")?;
+    let prefix = format!("{}-", mcv);
+
+    let mut versions: Vec<String> = re
+        .captures_iter(metadata.as_str())
+        .filter_map(|cap| cap[1].strip_prefix(prefix.as_str()).map(|fv| fv.to_string()))
+        .collect();
+
+    versions.sort_by(|a, b| ComparableVersion::parse(b).cmp(&ComparableVersion::parse(a)));
+    Ok(versions)
+}
+
+// forge version to install for a minecraft version
+pub async fn resolve_forge_version(mcv: &str, selector: &ForgeSelector) -> Result<String, Box<dyn Error>> {
+    let versions = get_forge_versions(mcv).await?;
+    if versions.is_empty() {
+        return Err(format!("There is no forge for minecraft {}", mcv).into());
+    }
+
+    match selector {
+        ForgeSelector::Recommended => {
+            // falls back to the latest promotion
+            match util::get_fv_from_mcv(mcv.to_string()).await {
+                Some(fv) => Ok(fv),
+                None => Ok(versions[0].clone()),
+            }
+        }
+        ForgeSelector::Latest => Ok(versions[0].clone()),
+        ForgeSelector::Version(version) => {
+            // accept the full maven version too
+            let fv = version.strip_prefix(format!("{}-", mcv).as_str()).unwrap_or(version);
+            if versions.iter().any(|v| v == fv) {
+                Ok(fv.to_string())
+            } else {
+                Err(format!("Forge {} doesn't exist for minecraft {}", fv, mcv).into())
+            }
+        }
+    }
+}
+
+// The installer names the version directory differently
+// depending on the forge release, e.g. `1.16.5-forge-36.2.39`
+// or `1.12.2-forge1.12.2-14.23.5.2768`
+pub fn find_forge_version(versions_path: &Path, mcv: &str, fv: &str) -> Option<String> {
+    let candidates = vec![
+        format!("{}-forge-{}", mcv, fv),
+        format!("{}-forge{}-{}", mcv, mcv, fv),
+    ];
+
+    for id in candidates {
+        if profile::json_path(versions_path, id.as_str()).exists() {
+            return Some(id);
+        }
+    }
+
+    // fall back to any forge version json mentioning this forge version
+    for entry in fs::read_dir(versions_path).ok()? {
+        let id = entry.ok()?.file_name().to_string_lossy().to_string();
+        if id.contains("forge") && id.contains(fv) && profile::json_path(versions_path, id.as_str()).exists() {
+            return Some(id);
+        }
+    }
+
+    None
+}
+
 pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
     let mut proj = CFProject::new(id, "https://api.cfwidget.com/".to_string());
 
@@ -100,8 +203,7 @@ pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
 
     let name = proj
         .files
-        .iter()
-        .nth(choice)
+        .get(choice)
         .unwrap()
         .clone()
         .name()
@@ -129,18 +231,22 @@ pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
     let manifest_json: serde_json::Value =
         serde_json::from_reader(manifest_file).expect("Manifest contains invalid json");
 
-    let mut modloader = manifest_json["minecraft"]["modLoaders"][0]["id"]
+    let pack_loader = manifest_json["minecraft"]["modLoaders"][0]["id"]
         .as_str()
         .unwrap();
-    
 
-    // check if modloader is in blacklist
-    for id in FORGE_PRE13_ID_BLACKLIST.iter() {
-        if *id == modloader {
-            modloader =  "forge-14.23.5.2855";
+    // some loader versions are swapped for ones that work
+    let modloader = match Config::load().loader_override(pack_loader) {
+        Some(loader_override) => {
+            println!(
+                "{} {} is replaced with {}",
+                Yellow.paint("Loader override:"), pack_loader, loader_override.to
+            );
+            info!("Replaced loader {} with {}", pack_loader, loader_override.to);
+            loader_override.to.clone()
         }
-    }
-
+        None => pack_loader.to_string(),
+    };
 
     // format is like `forge-${version}`
     let modloader_split: Vec<&str> = modloader.split('-').collect();
@@ -153,6 +259,109 @@ pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
     let mcv = manifest_json["minecraft"]["version"].as_str().unwrap();
     let fv = modloader_split[1];
 
+    let profile = install_forge(&instance, mcv, fv).await;
+    let rules = RuleContext::current();
+
+    let mut mods_path = instance.get_path();
+    mods_path.push("mods");
+
+    let mpc = mods_path.clone();
+    let mcvc = mcv.to_owned();
+    let downloads = setup::get_mod_downloads(mcvc, mpc).await.unwrap();
+
+    Downloader::new(downloads)
+        .process()
+        .await
+        .expect("Unable to finish download");
+
+    let mut overrides_path = mods_path;
+    overrides_path.push("overrides");
+
+    util::copy_overrides(instance.get_path(), overrides_path);
+
+    create_invoker(&instance, &profile, &rules, user_path).await;
+}
+
+// Forge instance for a minecraft and forge version of the
+// user's choice, without a modpack
+pub async fn setup_plain(mut ima: InstanceManager, user_path: PathBuf) {
+    let manifest = VersionManifest::fetch()
+        .await
+        .expect("Unable to get the version manifest");
+
+    println!("Minecraft version (or {}): ", versions::LATEST_RELEASE);
+    let mcv = match manifest.resolve(util::get_line().trim()) {
+        Some(version) => version.id.clone(),
+        None => {
+            println!("{}", Red.paint("Version not found. Exiting..."));
+            return;
+        }
+    };
+
+    let forge_versions = match get_forge_versions(mcv.as_str()).await {
+        Ok(val) => val,
+        Err(e) => {
+            println!("{} {}", Red.paint("Unable to get forge versions:"), e);
+            return;
+        }
+    };
+
+    if forge_versions.is_empty() {
+        println!("{} {}", Red.paint("There is no forge for minecraft"), mcv);
+        return;
+    }
+
+    let newest: Vec<&str> = forge_versions.iter().take(10).map(|v| v.as_str()).collect();
+    println!("Newest forge versions: {}", newest.join(", "));
+
+    println!("Forge version (recommended, latest or a version) [recommended]: ");
+    let selector = ForgeSelector::parse(util::get_line().as_str());
+
+    let fv = match resolve_forge_version(mcv.as_str(), &selector).await {
+        Ok(val) => val,
+        Err(e) => {
+            println!("{} {}", Red.paint("Invalid forge version:"), e);
+            return;
+        }
+    };
+
+    info!("Installing forge {} for minecraft {}", fv, mcv);
+
+    let instance = ima
+        .create_instance(format!("forge-{}-{}", mcv, fv))
+        .expect("Error creating instance");
+
+    let profile = install_forge(&instance, mcv.as_str(), fv.as_str()).await;
+    create_invoker(&instance, &profile, &RuleContext::current(), user_path).await;
+}
+
+// forge versions of a minecraft version, promoted ones marked
+pub async fn display_forge_versions(mcv: &str) -> Result<(), Box<dyn Error>> {
+    let forge_versions = get_forge_versions(mcv).await?;
+    let recommended = util::get_forge_promo(mcv, "recommended").await;
+    let latest = util::get_forge_promo(mcv, "latest").await;
+
+    let mut table = Table::new();
+    table.add_row(row!("VERSION", "PROMOTION"));
+
+    for fv in forge_versions {
+        let promotion = if recommended.as_ref() == Some(&fv) {
+            "recommended"
+        } else if latest.as_ref() == Some(&fv) {
+            "latest"
+        } else {
+            ""
+        };
+        table.add_row(row!(fv, promotion));
+    }
+
+    table.printstd();
+    Ok(())
+}
+
+// Run the forge installer for `mcv`-`fv` in an instance and
+// install the version it creates
+async fn install_forge(instance: &Instance, mcv: &str, fv: &str) -> VersionProfile {
     let mc_forge_version = format!("{}-{}", mcv, fv);
 
     let mut launcher_profiles_path = instance.get_path();
@@ -165,7 +374,7 @@ pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
     // forge headless installer for 1.13.2+
     // no need for headless installer here as forge
     // supports -installClient pre 1.13.2
-    let is_pre_13 = !util::geq_version(mcv, "1.13.2");
+    let is_pre_13 = ComparableVersion::parse(mcv) < ComparableVersion::parse("1.13.2");
 
     if is_pre_13 {
         let installer_cp = match cfg!(windows) {
@@ -173,7 +382,7 @@ pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
             false => format!("forge-{}-installer.jar",mc_forge_version)
         };
 
-        run_forge_installation(instance.get_path(), installer_cp, false);
+        run_forge_installation(instance.get_path(), installer_cp, java::java_for_component("jre-legacy", 8).await.as_str(), false);
     }else{
         info!("POST 1.13.2");
         download_headless_installer(instance.get_path()).await;
@@ -182,190 +391,45 @@ pub async fn setup(mut ima: InstanceManager, id: u64, user_path: PathBuf) {
             false =>  format!("forge-{}-installer.jar:forge-installer-headless-1.0.1.jar",mc_forge_version)
         };
 
-        run_forge_installation(instance.get_path(), installer_cp, true);
+        // newer installers run processors built for java 17
+        run_forge_installation(instance.get_path(), installer_cp, java::java_for_component("java-runtime-gamma", 17).await.as_str(), true);
     }
 
-    let mut mods_path = instance.get_path();
-    mods_path.push("mods");
-
-    let mut libpath = instance.get_path();
-    libpath.push("libraries");
-
-    let mut binpath = instance.get_path();
-    binpath.push("bin");
+    let mut versions_path = instance.get_path();
+    versions_path.push("versions");
 
-    let mut assets_path = instance.get_path();
-    assets_path.push("assets");
+    let forge_id = find_forge_version(&versions_path, mcv, fv)
+        .expect("Couldn't find the installed forge version");
 
-    let mut forge_version_path = instance.get_path();
-    forge_version_path.push(format!(
-        "versions/{}-forge-{}/{}-forge-{}.json",
-        mcv, fv, mcv, fv
-    ));
+    let profile = VersionProfile::load(&versions_path, forge_id.as_str())
+        .expect("Unable to load forge version json");
 
-    let mut vanilla_version_path = instance.get_path();
-    vanilla_version_path.push(format!("versions/{}/{}.json", mcv, mcv));
+    setup::install_version(instance.get_path(), &profile, &RuleContext::current()).await;
 
-    let version_paths = vec![vanilla_version_path.clone(), forge_version_path.clone()];
-
-    let vvpc = vanilla_version_path.clone();
-    let ip = instance.get_path();
-    tokio::spawn(async move {
-        setup::get_binaries(vvpc, ip).await;
-    });
-    
-    
-    // get libraries for both vanilla and forge
-    let vvpc = vanilla_version_path.clone();
-    let fvpc = forge_version_path.clone();
-
-    // get a list of all downloads
-    let mut downloads = setup::get_library_downloads(libpath.clone(), vvpc).await.unwrap();
-    downloads.extend(setup::get_library_downloads(libpath.clone(), fvpc).await.unwrap());
-
-    let mpc = mods_path.clone();
-    let mcvc = mcv.to_owned();
-    downloads.extend(setup::get_mod_downloads(mcvc, mpc).await.unwrap());
-    downloads.extend(
-        setup::get_asset_downloads(instance.get_path(), vanilla_version_path)
-            .await
-            .unwrap()
-    );
-
-    //let mut downloads_log_file_path = instance.get_path();
-    //downloads_log_file_path.push("downloads.log");
-
-    // no need for logging anymore
-
-    //let mut downloads_log_file = File::create(downloads_log_file_path).expect("Couldn't create file");
-
-    //for (k,v) in downloads.clone().into_iter() {
-    //    let line = format!("{},{}\n", k.display(),v);
-    //    downloads_log_file.write(line.as_bytes()).expect("Error writing to file");
-    //}
-
-    Downloader::new(downloads)
-        .process()
-        .await
-        .expect("Unable to finish download");
-
-    let mut overrides_path = mods_path;
-    overrides_path.push("overrides");
-
-    util::copy_overrides(instance.get_path(), overrides_path);
+    profile
+}
 
+async fn create_invoker(instance: &Instance, profile: &VersionProfile, rules: &RuleContext, user_path: PathBuf) {
     let user = User::from(user_path);
 
-    let forge_json_file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(forge_version_path)
-        .expect("Couldn't open forge version file");
-
-    let forge_json: serde_json::Value =
-        serde_json::from_reader(forge_json_file).expect("Unable to parse forge json file");
-
-    let mut vanilla_version_path = instance.get_path();
-    vanilla_version_path.push(format!("versions/{}/{}.json", mcv, mcv));
-    
-    let version_file = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .open(vanilla_version_path)
-        .unwrap();
-    let version : serde_json::Value = serde_json::from_reader(version_file).expect("Unable to parse version file");
-    let asset_index =  version["assetIndex"]["id"].as_str().unwrap();
-
-    let main_class = forge_json["mainClass"]
-        .as_str()
-        .expect("Couldn't get main class");
-
-    let forge_args = util::get_forge_args(forge_json.clone(), is_pre_13);
-
-    let classes = setup::get_cp_from_version(PathBuf::from("libraries"), version_paths);
-    let mut classpaths: Vec<PathBuf> = Vec::new();
-
-    for class in classes {
-        classpaths.push(class.1);
-    }
-
-    if is_pre_13 {
-        // Game args look like this
-        //"minecraftArguments": "--username ${auth_player_name} --version ${version_name} --gameDir ${game_directory} --assetsDir ${assets_root} --assetIndex ${assets_index_name} --uuid ${auth_uuid} --accessToken ${auth_access_token} --userType ${user_type} --tweakClass net.minecraftforge.fml.common.launcher.FMLTweaker --versionType Forge", 
-
-        let mut args = forge_json["minecraftArguments"].as_str().unwrap().to_string();
-
-        // download text2speech narrator 1.10.2
-       
-        // THIS IS VERY HACKY
-        // but a fix for now...
-        let mut download_map = HashMap::new();
-        let mut narrator_path = instance.get_path();
-        narrator_path.push("libraries/com/mojang/text2speech/1.10.3/text2speech-1.10.3.jar");
-        let narrator_url =  "https://libraries.minecraft.net/com/mojang/text2speech/1.10.3/text2speech-1.10.3.jar".to_string();
-        download_map.insert(narrator_path, narrator_url);
-
-        Downloader::new(download_map)
-            .process()
-            .await
-            .unwrap();
-
-        // com/mojang/text2speech/1.10.3/text2speech-1.10.3.jar
-        //https://libraries.minecraft.net/com/mojang/text2speech/1.10.3/text2speech-1.10.3.jar 
-        
-        // build game args 
-        args = args.replace("${auth_player_name}", user.name.as_str());
-        args = args.replace("${version_name}", proj.files[choice].version.as_str());
-        args = args.replace("${game_directory}", " . ");
-        args = args.replace("${assets_root}", " ./assets ");
-        args = args.replace("${assets_index_name}", asset_index);
-        args = args.replace("${auth_uuid}", user.id.as_str());
-        args = args.replace("${auth_access_token}", user.token.as_str());
-        args = args.replace("${user_type}", "mojang");
-
-        // First time setup
-        // using relative binpath
-        let mut invoker = Invoker::new(
-                "java".to_string(),
-                PathBuf::from("./bin"),
-                classpaths.clone(),
-                args,
-                main_class.to_string(),
-                instance.name(),
-                InstanceType::Forge,
-                user.name,
-                user.token,
-                user.id.to_string()
-            );
+    let classpaths = setup::get_classpath(instance.get_path(), profile, rules);
 
-        let mut invoker_file_path = instance.get_path();
-        invoker_file_path.push("sml_invoker.json");
+    // First time setup
+    // using relative binpath
+    let mut invoker = Invoker::new(
+            java::java_for_profile(profile).await,
+            classpaths,
+            profile,
+            Arguments::from_profile(profile, rules, &instance.get_path()),
+            instance.name(),
+            InstanceType::Forge,
+            &user
+        );
 
-        invoker.gen_invocation();
-        invoker.export_as_json(invoker_file_path);
+    let mut invoker_file_path = instance.get_path();
+    invoker_file_path.push("sml_invoker.json");
 
-    }else{
-
-        // POST 1.13.2
-        let mut invoker = Invoker::new(
-                "java ".to_string(),
-                PathBuf::from("bin"),
-                classpaths,
-                format!("{} --assetsDir ./assets --assetIndex {} --gameDir . --version  {}  --versionType release --userType mojang", forge_args.unwrap(), asset_index, proj.files[choice].version),
-                main_class.to_string(),
-                instance.name(),
-                InstanceType::Forge,
-                user.name,
-                user.token,
-                user.id.to_string()
-            );
-
-        let mut invoker_file_path = instance.get_path();
-        invoker_file_path.push("sml_invoker.json");
-
-        invoker.export_as_json(invoker_file_path);
-
-    }
+    invoker.export_as_json(invoker_file_path);
 
     info!("{}", Green.paint("Setup is complete!"));
 }
@@ -44,14 +44,14 @@ pub fn mods_len(mods_path: PathBuf) -> u64 {
 
 pub fn pause() {
     let mut stdout = io::stdout();
-    stdout.write(b"Press Enter to continue...").unwrap();
+    stdout.write_all(b"Press Enter to continue...").unwrap();
     stdout.flush().unwrap();
-    io::stdin().read(&mut [0]).unwrap();
+    io::stdin().read_exact(&mut [0]).unwrap();
 }
 
 pub fn get_instances_path() -> Option<PathBuf> {
     if cfg!(windows) {
-        return std::env::current_exe().ok().and_then(|mut pb| {
+        std::env::current_exe().ok().map(|mut pb| {
             pb.pop();
             pb.push("instances");
             // if path dne then, create one
@@ -60,8 +60,8 @@ pub fn get_instances_path() -> Option<PathBuf> {
                 println!("Creating instances dir at {}", pb.display());
                 fs::create_dir_all(pb.clone()).expect("Error creating instances folder");
             }
-            return Some(pb);
-        });
+            pb
+        })
     } else {
         // assuming linux here
         match std::env::var("HOME") {
@@ -70,69 +70,40 @@ pub fn get_instances_path() -> Option<PathBuf> {
                 pb.push(".local/share/sml/instances");
                 fs::create_dir_all(pb.clone()).expect("Unable to create sml dir");
 
-                return Some(pb);
+                Some(pb)
             }
-            Err(_) => return None,
-        };
+            Err(_) => None,
+        }
     }
 }
-pub fn get_u64() -> Option<u64> {
-    let mut input_text = String::new();
-    io::stdin()
-        .read_line(&mut input_text)
-        .expect("Failed to get input");
-
-    Some(
-        input_text
-            .trim()
-            .parse::<u64>()
-            .expect("Error parsing number"),
-    )
+// sml data directory, the parent of the instances directory
+pub fn get_sml_path() -> Option<PathBuf> {
+    get_instances_path().map(|mut pb| {
+        pb.pop();
+        pb
+    })
 }
 
-pub fn geq_version(version1: &str, version2: &str) -> bool {
-    // serialize version as int and check if greater
-    let v1_c: Vec<&str> = version1.split(".").collect();
-    let v2_c: Vec<&str> = version2.split(".").collect();
-
-    // 1.v1_c[1].x  > 1.v2_c[1].x
-
-    let mut v1 = 0;
-    let mut v2 = 0;
-    
-    for i in v1_c {
-        v1 =  v1 * 10 +  match i.parse::<i32>(){
-            Ok(val) => val,
-            Err(_) => 0 
-        };
-    }
+// None when nothing was entered
+pub fn get_u64() -> Option<u64> {
+    let input_text = get_line();
+    let input = input_text.trim();
 
-    for i in v2_c {
-        v2 = v2 * 10 + match i.parse::<i32>(){
-            Ok(val) => val,
-            Err(_) =>  0
-                //panic!(" v1:{}, v2:{} ",v1,v2)
-        };
+    if input.is_empty() {
+        return None;
     }
 
-    v1 >= v2
+    Some(input.parse::<u64>().expect("Error parsing number"))
 }
 
-pub fn get_forge_args(json: serde_json::Value, is_pre_13 : bool) -> Option<String> {
-    let mut retstr = String::new();
-    // get args here
-    if is_pre_13{
-        // parse args here
-    }else{
-        let args = json["arguments"]["game"].as_array().unwrap();
-        for arg in args.iter() {
-            retstr.push(' ');
-            retstr.push_str(arg.as_str().unwrap());
-        }
-    }
-    
+// read a line from stdin without the line ending
+pub fn get_line() -> String {
+    let mut input_text = String::new();
+    io::stdin()
+        .read_line(&mut input_text)
+        .expect("Failed to get input");
 
-    Some(retstr)
+    input_text.trim_end_matches(&['\r', '\n'][..]).to_string()
 }
 
 pub fn copy_overrides(instance_path: PathBuf, overrides_path: PathBuf) {
@@ -140,24 +111,32 @@ pub fn copy_overrides(instance_path: PathBuf, overrides_path: PathBuf) {
         .expect("Could not copy overrides");
 }
 
-pub async fn get_fv_from_mcv(mcv: String) -> String {
+// Forge version promoted for a minecraft version, the
+// recommended one or the latest if nothing is recommended yet
+pub async fn get_fv_from_mcv(mcv: String) -> Option<String> {
+    match get_forge_promo(mcv.as_str(), "recommended").await {
+        Some(fv) => Some(fv),
+        None => get_forge_promo(mcv.as_str(), "latest").await,
+    }
+}
+
+// `promo` is either recommended or latest
+pub async fn get_forge_promo(mcv: &str, promo: &str) -> Option<String> {
     let versions_url =
-        "https://files.minecraftforge.net/maven/net/minecraftforge/forge/promotions_slim.json";
+        "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
 
     let resp = reqwest::get(reqwest::Url::parse(versions_url).unwrap())
             .await
-            .unwrap()
+            .ok()?
             .text()
             .await
-            .unwrap();
+            .ok()?;
 
-    let versions_json: serde_json::Value = serde_json::from_str(resp.as_str()).unwrap();
+    let versions_json: serde_json::Value = serde_json::from_str(resp.as_str()).ok()?;
 
-    let key = format!("{}-recommended", mcv);
-    versions_json["promos"][key]
+    versions_json["promos"][format!("{}-{}", mcv, promo)]
         .as_str()
-        .expect("Couldn't get forge versions list")
-        .to_string()
+        .map(|fv| fv.to_string())
 }
 
 pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<()> {
@@ -0,0 +1,240 @@
+use std::error::Error;
+use std::fmt;
+
+use prettytable::Table;
+use regex::Regex;
+use serde::Deserialize;
+
+// Mojang's list of every released minecraft version.
+
+pub const VERSION_MANIFEST_URL: &str = "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+// aliases accepted wherever a version id is
+pub const LATEST_RELEASE: &str = "latest-release";
+pub const LATEST_SNAPSHOT: &str = "latest-snapshot";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VersionType {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+    // a type mojang added later, never listed
+    #[serde(other)]
+    Unknown,
+}
+
+impl VersionType {
+    pub fn parse(s: &str) -> Option<VersionType> {
+        match s.trim().to_lowercase().as_str() {
+            "release" => Some(VersionType::Release),
+            "snapshot" => Some(VersionType::Snapshot),
+            "old_beta" | "beta" => Some(VersionType::OldBeta),
+            "old_alpha" | "alpha" => Some(VersionType::OldAlpha),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VersionType::Release => "release",
+            VersionType::Snapshot => "snapshot",
+            VersionType::OldBeta => "old_beta",
+            VersionType::OldAlpha => "old_alpha",
+            VersionType::Unknown => "unknown",
+        }
+    }
+}
+
+impl fmt::Display for VersionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionEntry {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub version_type: VersionType,
+    // version json
+    pub url: String,
+    pub time: String,
+    pub release_time: String,
+    pub sha1: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Latest {
+    pub release: String,
+    pub snapshot: String,
+}
+
+// What to list, everything by default. `from` and `to` are
+// version ids (or aliases) and include the versions themselves.
+#[derive(Clone, Debug, Default)]
+pub struct VersionFilter {
+    pub types: Vec<VersionType>,
+    pub pattern: Option<Regex>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+// newest first, like the manifest itself
+#[derive(Clone, Debug, Deserialize)]
+pub struct VersionManifest {
+    pub latest: Latest,
+    pub versions: Vec<VersionEntry>,
+}
+
+impl VersionManifest {
+    pub async fn fetch() -> Result<VersionManifest, Box<dyn Error>> {
+        let body = reqwest::get(VERSION_MANIFEST_URL).await?.text().await?;
+        Ok(serde_json::from_str(body.as_str())?)
+    }
+
+    pub fn find(&self, id: &str) -> Option<&VersionEntry> {
+        self.versions.iter().find(|v| v.id == id)
+    }
+
+    // a version id, latest-release or latest-snapshot
+    pub fn resolve(&self, id: &str) -> Option<&VersionEntry> {
+        match id {
+            LATEST_RELEASE => self.find(self.latest.release.as_str()),
+            LATEST_SNAPSHOT => self.find(self.latest.snapshot.as_str()),
+            _ => self.find(id),
+        }
+    }
+
+    pub fn filter(&self, filter: &VersionFilter) -> Result<Vec<&VersionEntry>, String> {
+        let release_time = |id: &Option<String>| -> Result<Option<String>, String> {
+            match id {
+                Some(id) => match self.resolve(id.as_str()) {
+                    Some(version) => Ok(Some(version.release_time.clone())),
+                    None => Err(format!("Unknown version {}", id)),
+                },
+                None => Ok(None),
+            }
+        };
+
+        // release times are all in the same format and timezone,
+        // so they can be compared as strings
+        let from = release_time(&filter.from)?;
+        let to = release_time(&filter.to)?;
+
+        Ok(self
+            .versions
+            .iter()
+            .filter(|v| v.version_type != VersionType::Unknown)
+            .filter(|v| filter.types.is_empty() || filter.types.contains(&v.version_type))
+            .filter(|v| filter.pattern.as_ref().is_none_or(|p| p.is_match(v.id.as_str())))
+            .filter(|v| from.as_ref().is_none_or(|from| v.release_time >= *from))
+            .filter(|v| to.as_ref().is_none_or(|to| v.release_time <= *to))
+            .collect())
+    }
+
+    pub fn of_type(&self, version_type: VersionType) -> Vec<&VersionEntry> {
+        self.versions
+            .iter()
+            .filter(|v| v.version_type == version_type)
+            .collect()
+    }
+}
+
+pub fn display_list(versions: &[&VersionEntry]) {
+    let mut table = Table::new();
+    table.add_row(row!("ID", "TYPE", "RELEASED"));
+
+    for version in versions {
+        // only the date of e.g. 2021-07-07T12:23:24+00:00
+        let released = version.release_time.get(0..10).unwrap_or(version.release_time.as_str());
+        table.add_row(row!(version.id, version.version_type, released));
+    }
+
+    table.printstd();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "latest": { "release": "1.20.1", "snapshot": "23w31a" },
+        "versions": [
+            { "id": "23w31a", "type": "snapshot", "url": "", "time": "", "releaseTime": "2023-08-01T09:14:34+00:00", "sha1": "" },
+            { "id": "1.20.1", "type": "release", "url": "", "time": "", "releaseTime": "2023-06-12T13:25:51+00:00", "sha1": "" },
+            { "id": "1.20", "type": "release", "url": "", "time": "", "releaseTime": "2023-06-02T08:36:17+00:00", "sha1": "" },
+            { "id": "1.20-pre7", "type": "snapshot", "url": "", "time": "", "releaseTime": "2023-05-31T13:17:25+00:00", "sha1": "" },
+            { "id": "1.19.4", "type": "release", "url": "", "time": "", "releaseTime": "2023-03-14T12:56:18+00:00", "sha1": "" },
+            { "id": "april", "type": "april_fools", "url": "", "time": "", "releaseTime": "2023-04-01T00:00:00+00:00", "sha1": "" },
+            { "id": "b1.7.3", "type": "old_beta", "url": "", "time": "", "releaseTime": "2011-07-07T22:00:00+00:00", "sha1": "" }
+        ]
+    }"#;
+
+    fn manifest() -> VersionManifest {
+        serde_json::from_str(MANIFEST).unwrap()
+    }
+
+    fn ids(versions: Vec<&VersionEntry>) -> Vec<&str> {
+        versions.iter().map(|v| v.id.as_str()).collect()
+    }
+
+    #[test]
+    fn unknown_types() {
+        let manifest = manifest();
+        assert_eq!(manifest.find("april").unwrap().version_type, VersionType::Unknown);
+
+        // listed nowhere, not even without a filter
+        let all = ids(manifest.filter(&VersionFilter::default()).unwrap());
+        assert_eq!(all.len(), 6);
+        assert!(!all.contains(&"april"));
+    }
+
+    #[test]
+    fn aliases() {
+        let manifest = manifest();
+        assert_eq!(manifest.resolve(LATEST_RELEASE).unwrap().id, "1.20.1");
+        assert_eq!(manifest.resolve(LATEST_SNAPSHOT).unwrap().id, "23w31a");
+        assert!(manifest.resolve("1.21").is_none());
+    }
+
+    #[test]
+    fn filters() {
+        let manifest = manifest();
+
+        let releases = VersionFilter {
+            types: vec![VersionType::Release],
+            ..VersionFilter::default()
+        };
+        assert_eq!(ids(manifest.filter(&releases).unwrap()), vec!["1.20.1", "1.20", "1.19.4"]);
+
+        let pattern = VersionFilter {
+            pattern: Some(Regex::new(r"^1\.20").unwrap()),
+            ..VersionFilter::default()
+        };
+        assert_eq!(ids(manifest.filter(&pattern).unwrap()), vec!["1.20.1", "1.20", "1.20-pre7"]);
+
+        // both ends are included
+        let range = VersionFilter {
+            from: Some("1.20-pre7".to_string()),
+            to: Some("1.20".to_string()),
+            ..VersionFilter::default()
+        };
+        assert_eq!(ids(manifest.filter(&range).unwrap()), vec!["1.20", "1.20-pre7"]);
+
+        let snapshots_since_release = VersionFilter {
+            types: vec![VersionType::Snapshot],
+            from: Some(LATEST_RELEASE.to_string()),
+            ..VersionFilter::default()
+        };
+        assert_eq!(ids(manifest.filter(&snapshots_since_release).unwrap()), vec!["23w31a"]);
+
+        let unknown = VersionFilter {
+            to: Some("1.2.5".to_string()),
+            ..VersionFilter::default()
+        };
+        assert!(manifest.filter(&unknown).is_err());
+    }
+}